@@ -1,6 +1,6 @@
 use slog::Logger;
 
-use codealong::{Repo, RepoInfo, Workspace};
+use codealong::{AnalysisState, Repo, RepoInfo, Workspace};
 
 use crate::analyze_repos::analyze_repos;
 use crate::build_workspace::build_workspace;
@@ -13,7 +13,10 @@ pub fn analyze(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
     let mut workspace = build_workspace(matches, logger)?;
     let repos = build_repos(&workspace, matches);
     initialize_repos(matches, repos.clone(), logger)?;
-    let results = analyze_repos(matches, repos.clone(), logger)?;
+    let mut state = AnalysisState::from_dir(workspace.dir())?;
+    let results = analyze_repos(matches, repos.clone(), &state, logger)?;
+    state.merge(results.state);
+    state.save(workspace.dir())?;
     if results.new_authors.len() > 0 {
         info!(logger, "Found additional contributors, adding to config");
         for contributor in results.new_authors {
@@ -31,7 +34,7 @@ fn validate_args(matches: &clap::ArgMatches) -> Result<()> {
     Ok(())
 }
 
-fn build_repos(workspace: &Workspace, matches: &clap::ArgMatches) -> Vec<Repo> {
+pub fn build_repos(workspace: &Workspace, matches: &clap::ArgMatches) -> Vec<Repo> {
     let skip_forks = matches.is_present("skip_forks");
     let mut repos: Vec<Repo> = workspace
         .repos()
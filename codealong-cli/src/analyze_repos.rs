@@ -1,4 +1,5 @@
-use std::collections::{HashSet, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::thread;
 
@@ -7,7 +8,9 @@ use chrono::Utc;
 use error_chain::ChainedError;
 use slog::Logger;
 
-use codealong::{AnalyzeOpts, Contributor, Repo, RepoAnalyzer};
+use codealong::{
+    AnalysisState, AnalyzeOpts, Contributor, Repo, RepoAnalyzer, RepoState, WorkerBudget,
+};
 use codealong_github::PullRequestsAnalyzer;
 
 use crate::error::Result;
@@ -17,17 +20,21 @@ use crate::ui::{NamedProgressBar, ProgressPool};
 pub fn analyze_repos(
     matches: &clap::ArgMatches,
     repos: Vec<Repo>,
+    state: &AnalysisState,
     logger: &Logger,
 ) -> Result<AnalyzeResults> {
     info!(logger, "Analyzing {} repos", repos.len());
-    let num_threads = std::cmp::min(
-        matches
-            .value_of("concurrency")
-            .unwrap_or_else(|| "6")
-            .parse::<i32>()?,
-        (repos.len() * 2) as i32,
-    );
-    let tasks = expand_tasks(&matches, repos);
+    let concurrency = matches
+        .value_of("concurrency")
+        .unwrap_or_else(|| "6")
+        .parse::<i32>()?;
+    let num_threads = std::cmp::min(concurrency, (repos.len() * 2) as i32);
+    // Threads not needed for tasks are lent to the commit analysis of
+    // individual repos, as are task threads once they run out of tasks.
+    let budget = Arc::new(WorkerBudget::new(
+        (concurrency - num_threads).max(0) as usize
+    ));
+    let tasks = expand_tasks(&matches, repos, state);
     let m = Arc::new(ProgressPool::new(
         tasks.len() as u64,
         matches.is_present("progress"),
@@ -40,6 +47,7 @@ pub fn analyze_repos(
         let results = results.clone();
         let tasks = tasks.clone();
         let m = m.clone();
+        let budget = budget.clone();
         let mut pb = m.add();
         let root_logger = logger.clone();
         threads.push(thread::spawn(move || loop {
@@ -50,7 +58,7 @@ pub fn analyze_repos(
             if let Some(task) = task {
                 let logger = root_logger.new(o!("repo" => task.repo.repo_info().name.to_owned()));
                 pb.reset(task.display_name().to_owned());
-                match task.analyze(&pb, &logger) {
+                match task.analyze(&pb, &budget, &logger) {
                     Ok(task_results) => {
                         let mut results = results.lock().unwrap();
                         results.merge(task_results);
@@ -62,6 +70,7 @@ pub fn analyze_repos(
                 m.inc(1);
             } else {
                 pb.finish();
+                budget.release();
                 break;
             }
         }));
@@ -76,24 +85,46 @@ pub fn analyze_repos(
 #[derive(Debug)]
 pub struct AnalyzeResults {
     pub new_authors: HashSet<Contributor>,
+    pub state: AnalysisState,
 }
 
 impl AnalyzeResults {
     fn new() -> AnalyzeResults {
         AnalyzeResults {
             new_authors: HashSet::new(),
+            state: AnalysisState::default(),
         }
     }
 
     fn merge(&mut self, other: AnalyzeResults) {
         self.new_authors.extend(other.new_authors);
+        self.state.merge(other.state);
     }
 }
 
-fn expand_tasks(matches: &clap::ArgMatches, repos: Vec<Repo>) -> VecDeque<AnalyzeTask> {
+fn expand_tasks(
+    matches: &clap::ArgMatches,
+    repos: Vec<Repo>,
+    state: &AnalysisState,
+) -> VecDeque<AnalyzeTask> {
     let mut tasks: VecDeque<AnalyzeTask> = VecDeque::new();
+    // submodules pointing to repos of the workspace are resolved in their
+    // clones
+    let submodule_repos: BTreeMap<String, PathBuf> = repos
+        .iter()
+        .map(|repo| (repo.repo_info().name.clone(), repo.path().to_owned()))
+        .collect();
     for repo in repos {
-        let opts = analyze_opts_from_args(&repo, matches).unwrap();
+        let mut opts = analyze_opts_from_args(&repo, matches).unwrap();
+        opts.submodule_repos = submodule_repos.clone();
+        if let Some(repo_state) = state.repo(&repo.repo_info().name) {
+            if matches.is_present("full") {
+                // pull requests are still linked to commits when re-analyzing
+                opts.state.pull_request_commits = repo_state.pull_request_commits.clone();
+            } else {
+                opts.state = repo_state.clone();
+            }
+        }
         if !matches.is_present("skip_commits") {
             tasks.push_back(AnalyzeTask {
                 repo: repo.clone(),
@@ -108,6 +139,13 @@ fn expand_tasks(matches: &clap::ArgMatches, repos: Vec<Repo>) -> VecDeque<Analyz
                 opts: opts.clone(),
             });
         }
+        if matches.is_present("releases") {
+            tasks.push_back(AnalyzeTask {
+                repo: repo.clone(),
+                task_type: AnalyzeTaskType::Release,
+                opts: opts.clone(),
+            });
+        }
     }
     tasks
 }
@@ -115,6 +153,7 @@ fn expand_tasks(matches: &clap::ArgMatches, repos: Vec<Repo>) -> VecDeque<Analyz
 enum AnalyzeTaskType {
     Commit,
     PullRequest,
+    Release,
 }
 
 struct AnalyzeTask {
@@ -124,10 +163,18 @@ struct AnalyzeTask {
 }
 
 impl AnalyzeTask {
-    fn analyze(&self, pb: &NamedProgressBar, logger: &Logger) -> Result<AnalyzeResults> {
+    fn analyze(
+        &self,
+        pb: &NamedProgressBar,
+        budget: &Arc<WorkerBudget>,
+        logger: &Logger,
+    ) -> Result<AnalyzeResults> {
         match self.task_type {
-            AnalyzeTaskType::Commit => analyze_commits(pb, &self.repo, self.opts.clone(), logger),
+            AnalyzeTaskType::Commit => {
+                analyze_commits(pb, &self.repo, self.opts.clone(), budget, logger)
+            }
             AnalyzeTaskType::PullRequest => analyze_prs(pb, &self.repo, self.opts.clone(), logger),
+            AnalyzeTaskType::Release => analyze_releases(pb, &self.repo, self.opts.clone(), logger),
         }
     }
 
@@ -140,28 +187,58 @@ fn analyze_commits(
     pb: &NamedProgressBar,
     repo: &Repo,
     opts: AnalyzeOpts,
+    budget: &Arc<WorkerBudget>,
     logger: &Logger,
 ) -> Result<AnalyzeResults> {
     info!(logger, "Analyzing commits");
     let analyzer = RepoAnalyzer::from_repo(repo, logger)?;
+    let tips = analyzer.ref_tips()?;
     let client = codealong_elk::Client::default();
     pb.set_message("calculating");
     let count = analyzer.guess_len(opts.clone())?;
     pb.set_length(count as u64);
     pb.set_message("analyzing commits");
     let mut results = AnalyzeResults::new();
-    for commit_analyzer in analyzer.analyze(opts)? {
-        let commit_analyzer = commit_analyzer?;
-        let analyzed_commit = commit_analyzer.analyze()?;
-        if !commit_analyzer.is_author_known() {
+    // commits before `since` were not analyzed, so recording the tips would
+    // hide them from later runs
+    let record_tips = opts.since.is_none();
+    // applied once every commit has been indexed, as the reverted and bug
+    // introducing commits are usually indexed after their revert or fix
+    let mut reverted_commits = Vec::new();
+    let mut bug_introducing_commits = Vec::new();
+    // commits split by project yield one part after another
+    let mut last_commit = None;
+    for analyzed_commit in analyzer.analyze_parallel(opts, budget.clone())? {
+        let analyzed_commit = analyzed_commit?;
+        if !analyzed_commit.is_author_known {
             results
                 .new_authors
                 .insert(analyzed_commit.normalized_author.clone().unwrap());
         }
+        reverted_commits.extend(analyzer.reverted_commit(&analyzed_commit));
+        bug_introducing_commits.extend(analyzer.bug_introducing_commits(&analyzed_commit));
+        if last_commit.as_ref() != Some(&analyzed_commit.id) {
+            last_commit = Some(analyzed_commit.id.clone());
+            pb.inc(1);
+        }
         client.index(analyzed_commit)?;
-        pb.inc(1);
+    }
+    for reverted_commit in reverted_commits {
+        client.update(reverted_commit)?;
+    }
+    for bug_introducing_commit in bug_introducing_commits {
+        client.append(bug_introducing_commit)?;
     }
     pb.finish();
+    if record_tips {
+        results.state.repos.insert(
+            repo.repo_info().name.to_owned(),
+            RepoState {
+                refs: tips,
+                ..RepoState::default()
+            },
+        );
+    }
     Ok(results)
 }
 
@@ -174,13 +251,16 @@ fn analyze_prs(
     info!(logger, "Analyzing pull requests");
     let github_client = codealong_github::Client::from_env();
     let analyzer = PullRequestsAnalyzer::from_repo(repo, &github_client, logger)?;
+    let repo_analyzer = RepoAnalyzer::from_repo(repo, logger)?;
     let client = codealong_elk::Client::default();
     pb.set_message("calculating");
     let count = analyzer.guess_len(opts.clone())?;
     pb.set_length(count as u64);
     pb.set_message("analyzing pull requests");
     let mut results = AnalyzeResults::new();
-    for pull_request_analyzer in analyzer.analyze(opts)? {
+    let mut updated_at = None;
+    let mut pull_request_commits = BTreeMap::new();
+    for pull_request_analyzer in analyzer.analyze(opts.clone())? {
         let pull_request_analyzer = pull_request_analyzer?;
         let is_known = pull_request_analyzer.is_author_known();
         let analyzed_pr = pull_request_analyzer.analyze()?;
@@ -189,10 +269,70 @@ fn analyze_prs(
                 .new_authors
                 .insert(analyzed_pr.normalized_author.clone());
         }
+        updated_at = updated_at.max(Some(analyzed_pr.pr.updated_at));
+        for commit_id in &analyzed_pr.commits {
+            pull_request_commits.insert(commit_id.clone(), analyzed_pr.pr.number);
+        }
+        // the commits have usually been indexed before their pull request
+        // was merged
+        for commit_update in
+            repo_analyzer.reviewed_commits(&analyzed_pr.commits, analyzed_pr.pr.number)?
+        {
+            client.update(commit_update)?;
+        }
         client.index(analyzed_pr)?;
         pb.inc(1);
     }
     pb.finish();
+    results.state.repos.insert(
+        repo.repo_info().name.to_owned(),
+        RepoState {
+            pull_requests_updated_at: updated_at,
+            pull_request_commits: Some(
+                repo_analyzer.unanalyzed_pull_request_commits(pull_request_commits, &opts.state)?,
+            ),
+            ..RepoState::default()
+        },
+    );
+    Ok(results)
+}
+
+fn analyze_releases(
+    pb: &NamedProgressBar,
+    repo: &Repo,
+    opts: AnalyzeOpts,
+    logger: &Logger,
+) -> Result<AnalyzeResults> {
+    info!(logger, "Analyzing releases");
+    let analyzer = RepoAnalyzer::from_repo(repo, logger)?;
+    let client = codealong_elk::Client::default();
+    let release_analyzer = analyzer.analyze_releases(opts.clone())?;
+    let count = release_analyzer
+        .tags()
+        .iter()
+        .filter(|tag| !opts.state.releases.contains(&tag.name))
+        .count();
+    pb.set_length(count as u64);
+    pb.set_message("analyzing releases");
+    let mut results = AnalyzeResults::new();
+    let mut releases = BTreeSet::new();
+    for release in release_analyzer {
+        let mut release = release?;
+        releases.insert(release.tag.clone());
+        for commit_update in std::mem::take(&mut release.commit_updates) {
+            client.update(commit_update)?;
+        }
+        client.index(release)?;
+        pb.inc(1);
+    }
+    pb.finish();
+    results.state.repos.insert(
+        repo.repo_info().name.to_owned(),
+        RepoState {
+            releases,
+            ..RepoState::default()
+        },
+    );
     Ok(results)
 }
 
@@ -210,5 +350,9 @@ fn analyze_opts_from_args(repo: &Repo, matches: &clap::ArgMatches) -> Result<Ana
         since,
         ignore_unknown_authors: matches.is_present("skip_unknown_authors")
             || repo.repo_info().fork && matches.is_present("skip_unknown_authors_in_forks"),
+        historical_config: matches.is_present("historical_config"),
+        cache: !matches.is_present("no_cache"),
+        blame_bug_fixes: matches.is_present("blame_bug_fixes"),
+        ..AnalyzeOpts::default()
     })
 }
@@ -0,0 +1,93 @@
+use chrono::Duration;
+use error_chain::ChainedError;
+use slog::Logger;
+
+use codealong::{BusFactor, BusFactorOpts, RepoAnalyzer};
+
+use crate::analyze::build_repos;
+use crate::build_workspace::build_workspace;
+use crate::error::Result;
+use crate::initialize_repos::initialize_repos;
+use crate::ui::print_table;
+use crate::utils::build_es_client;
+
+pub fn bus_factor(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let workspace = build_workspace(matches, logger)?;
+    let repos = build_repos(&workspace, matches);
+    initialize_repos(matches, repos.clone(), logger)?;
+    let client = build_es_client(matches);
+    let opts = bus_factor_opts_from_args(matches)?;
+    let mut rows = Vec::new();
+    for repo in repos {
+        let name = repo.repo_info().name.to_owned();
+        let logger = logger.new(o!("repo" => name.clone()));
+        info!(logger, "Analyzing bus factor");
+        let result = RepoAnalyzer::from_repo(&repo, &logger)
+            .and_then(|analyzer| analyzer.analyze_bus_factor(matches.value_of("ref"), &opts));
+        match result {
+            Ok(bus_factors) => {
+                for bus_factor in bus_factors {
+                    client.index(bus_factor.clone())?;
+                    rows.push((name.clone(), bus_factor));
+                }
+            }
+            Err(e) => {
+                error!(logger, "error analyzing bus factor"; "error" => e.display_chain().to_string());
+            }
+        }
+    }
+    print_bus_factors(&rows);
+    Ok(())
+}
+
+fn bus_factor_opts_from_args(matches: &clap::ArgMatches) -> Result<BusFactorOpts> {
+    let threshold = matches.value_of("threshold").unwrap().parse::<u32>()?;
+    let inactive_for = humantime::parse_duration(matches.value_of("inactive_for").unwrap())?;
+    Ok(BusFactorOpts {
+        threshold: f64::from(threshold) / 100.0,
+        inactive_after: Duration::seconds(inactive_for.as_secs() as i64),
+        ..BusFactorOpts::default()
+    })
+}
+
+/// Prints the bus factor of each area, lowest first. Inactive owners are
+/// marked with a `*`.
+fn print_bus_factors(rows: &[(String, BusFactor)]) {
+    let mut rows: Vec<&(String, BusFactor)> = rows.iter().collect();
+    rows.sort_by(|(a_repo, a), (b_repo, b)| {
+        a.bus_factor
+            .cmp(&b.bus_factor)
+            .then_with(|| b.at_risk.cmp(&a.at_risk))
+            .then_with(|| a_repo.cmp(b_repo))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    let rows: Vec<Vec<String>> = rows
+        .into_iter()
+        .map(|(repo, bus_factor)| {
+            let owners: Vec<String> = bus_factor
+                .owners
+                .iter()
+                .map(|owner| {
+                    format!(
+                        "{}{} ({}%)",
+                        owner.id,
+                        if owner.inactive { "*" } else { "" },
+                        owner.lines * 100 / bus_factor.lines.max(1)
+                    )
+                })
+                .collect();
+            vec![
+                repo.clone(),
+                format!("/{}", bus_factor.path),
+                bus_factor.lines.to_string(),
+                bus_factor.bus_factor.to_string(),
+                if bus_factor.at_risk { "yes" } else { "" }.to_owned(),
+                owners.join(", "),
+            ]
+        })
+        .collect();
+    print_table(
+        &["REPO", "PATH", "LINES", "BUS FACTOR", "AT RISK", "OWNERS"],
+        &rows,
+    );
+}
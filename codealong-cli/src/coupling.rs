@@ -0,0 +1,96 @@
+use chrono::prelude::*;
+use error_chain::ChainedError;
+use slog::Logger;
+
+use codealong::{AnalysisState, Coupling, CouplingOpts, Repo, RepoAnalyzer};
+
+use crate::analyze::build_repos;
+use crate::build_workspace::build_workspace;
+use crate::error::Result;
+use crate::initialize_repos::initialize_repos;
+use crate::ui::print_table;
+use crate::utils::build_es_client;
+
+pub fn coupling(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let workspace = build_workspace(matches, logger)?;
+    let repos = build_repos(&workspace, matches);
+    initialize_repos(matches, repos.clone(), logger)?;
+    let state = AnalysisState::from_dir(workspace.dir())?;
+    let client = build_es_client(matches);
+    let top = matches.value_of("top").unwrap().parse::<usize>()?;
+    for repo in repos {
+        let logger = logger.new(o!("repo" => repo.repo_info().name.to_owned()));
+        let mut opts = coupling_opts_from_args(matches)?;
+        if let Some(repo_state) = state.repo(&repo.repo_info().name) {
+            opts.pull_request_commits = repo_state.pull_request_commits.clone().unwrap_or_default();
+        }
+        match index_couplings(matches, &repo, &opts, &client, &logger) {
+            Ok(couplings) => print_couplings(
+                &repo.repo_info().name,
+                &couplings[..top.min(couplings.len())],
+            ),
+            Err(e) => {
+                error!(logger, "error analyzing coupling"; "error" => e.display_chain().to_string());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn index_couplings(
+    matches: &clap::ArgMatches,
+    repo: &Repo,
+    opts: &CouplingOpts,
+    client: &codealong_elk::Client,
+    logger: &Logger,
+) -> Result<Vec<Coupling>> {
+    info!(logger, "Analyzing coupling");
+    let analyzer = RepoAnalyzer::from_repo(repo, logger)?;
+    let couplings = analyzer.analyze_coupling(matches.value_of("ref"), opts)?;
+    for coupling in &couplings {
+        client.index(coupling.clone())?;
+    }
+    Ok(couplings)
+}
+
+fn coupling_opts_from_args(matches: &clap::ArgMatches) -> Result<CouplingOpts> {
+    let since = if let Some(since) = matches.value_of("since") {
+        let duration = humantime::parse_duration(since)?;
+        Some(Utc.timestamp(Utc::now().timestamp() - duration.as_secs() as i64, 0))
+    } else {
+        None
+    };
+    let min_confidence = matches.value_of("min_confidence").unwrap().parse::<u32>()?;
+    Ok(CouplingOpts {
+        since,
+        by_pull_request: matches.is_present("by_pull_request"),
+        min_support: matches.value_of("min_support").unwrap().parse::<usize>()?,
+        min_confidence: f64::from(min_confidence) / 100.0,
+        ..CouplingOpts::default()
+    })
+}
+
+/// Prints the most strongly coupled pairs. Directories end with a `/`.
+fn print_couplings(repo: &str, couplings: &[Coupling]) {
+    println!("{}", repo);
+    let display = |path: &str, directory: bool| {
+        if directory {
+            format!("{}/", path)
+        } else {
+            path.to_owned()
+        }
+    };
+    let rows: Vec<Vec<String>> = couplings
+        .iter()
+        .map(|coupling| {
+            vec![
+                format!("{}%", (coupling.strength * 100.0).round()),
+                coupling.co_changes.to_string(),
+                display(&coupling.path, coupling.directory),
+                display(&coupling.coupled_path, coupling.directory),
+            ]
+        })
+        .collect();
+    print_table(&["STRENGTH", "CO-CHANGES", "PATH", "COUPLED PATH"], &rows);
+    println!();
+}
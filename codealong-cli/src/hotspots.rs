@@ -0,0 +1,77 @@
+use chrono::prelude::*;
+use error_chain::ChainedError;
+use slog::Logger;
+
+use codealong::{Hotspot, HotspotOpts, Repo, RepoAnalyzer};
+
+use crate::analyze::build_repos;
+use crate::build_workspace::build_workspace;
+use crate::error::Result;
+use crate::initialize_repos::initialize_repos;
+use crate::ui::print_table;
+use crate::utils::build_es_client;
+
+pub fn hotspots(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let workspace = build_workspace(matches, logger)?;
+    let repos = build_repos(&workspace, matches);
+    initialize_repos(matches, repos.clone(), logger)?;
+    let client = build_es_client(matches);
+    let opts = hotspot_opts_from_args(matches)?;
+    let top = matches.value_of("top").unwrap().parse::<usize>()?;
+    for repo in repos {
+        let logger = logger.new(o!("repo" => repo.repo_info().name.to_owned()));
+        match index_hotspots(matches, &repo, &opts, &client, &logger) {
+            Ok(hotspots) => {
+                print_hotspots(&repo.repo_info().name, &hotspots[..top.min(hotspots.len())])
+            }
+            Err(e) => {
+                error!(logger, "error analyzing hotspots"; "error" => e.display_chain().to_string());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn index_hotspots(
+    matches: &clap::ArgMatches,
+    repo: &Repo,
+    opts: &HotspotOpts,
+    client: &codealong_elk::Client,
+    logger: &Logger,
+) -> Result<Vec<Hotspot>> {
+    info!(logger, "Analyzing hotspots");
+    let analyzer = RepoAnalyzer::from_repo(repo, logger)?;
+    let hotspots = analyzer.analyze_hotspots(matches.value_of("ref"), opts)?;
+    for hotspot in &hotspots {
+        client.index(hotspot.clone())?;
+    }
+    Ok(hotspots)
+}
+
+fn hotspot_opts_from_args(matches: &clap::ArgMatches) -> Result<HotspotOpts> {
+    let since = if let Some(since) = matches.value_of("since") {
+        let duration = humantime::parse_duration(since)?;
+        Some(Utc.timestamp(Utc::now().timestamp() - duration.as_secs() as i64, 0))
+    } else {
+        None
+    };
+    Ok(HotspotOpts { since })
+}
+
+fn print_hotspots(repo: &str, hotspots: &[Hotspot]) {
+    println!("{}", repo);
+    let rows: Vec<Vec<String>> = hotspots
+        .iter()
+        .map(|hotspot| {
+            vec![
+                hotspot.rank.to_string(),
+                hotspot.path.clone(),
+                hotspot.changes.to_string(),
+                hotspot.lines.to_string(),
+                hotspot.score.to_string(),
+            ]
+        })
+        .collect();
+    print_table(&["RANK", "PATH", "CHANGES", "LINES", "SCORE"], &rows);
+    println!();
+}
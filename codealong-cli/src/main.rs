@@ -16,18 +16,28 @@ extern crate sloggers;
 mod analyze;
 mod analyze_repos;
 mod build_workspace;
+mod bus_factor;
+mod coupling;
 mod error;
+mod hotspots;
 mod init;
 mod initialize_repos;
 mod logger;
+mod ownership;
+mod survival;
 mod ui;
 mod utils;
 
 use error_chain::ChainedError;
 
 use crate::analyze::analyze;
+use crate::bus_factor::bus_factor;
+use crate::coupling::coupling;
+use crate::hotspots::hotspots;
 use crate::init::init;
 use crate::logger::build_logger;
+use crate::ownership::ownership;
+use crate::survival::survival;
 
 fn main() {
     use clap::{App, AppSettings};
@@ -48,6 +58,41 @@ fn main() {
         }).unwrap();
     }
 
+    if let Some(matches) = matches.subcommand_matches("ownership") {
+        ownership(matches, &logger).map_err(|e| {
+            error!(logger, "error invoking ownership subcommand"; "error" => e.display_chain().to_string());
+            e
+        }).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("bus-factor") {
+        bus_factor(matches, &logger).map_err(|e| {
+            error!(logger, "error invoking bus-factor subcommand"; "error" => e.display_chain().to_string());
+            e
+        }).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("survival") {
+        survival(matches, &logger).map_err(|e| {
+            error!(logger, "error invoking survival subcommand"; "error" => e.display_chain().to_string());
+            e
+        }).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("hotspots") {
+        hotspots(matches, &logger).map_err(|e| {
+            error!(logger, "error invoking hotspots subcommand"; "error" => e.display_chain().to_string());
+            e
+        }).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("coupling") {
+        coupling(matches, &logger).map_err(|e| {
+            error!(logger, "error invoking coupling subcommand"; "error" => e.display_chain().to_string());
+            e
+        }).unwrap();
+    }
+
     if let Some(matches) = matches.subcommand_matches("init") {
         init(matches, &logger).map_err(|e| {
             error!(logger, "error invoking init subcommand"; "error" => e.display_chain().to_string());
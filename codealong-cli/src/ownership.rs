@@ -0,0 +1,38 @@
+use error_chain::ChainedError;
+use slog::Logger;
+
+use codealong::{Repo, RepoAnalyzer};
+
+use crate::analyze::build_repos;
+use crate::build_workspace::build_workspace;
+use crate::error::Result;
+use crate::initialize_repos::initialize_repos;
+use crate::utils::build_es_client;
+
+pub fn ownership(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let workspace = build_workspace(matches, logger)?;
+    let repos = build_repos(&workspace, matches);
+    initialize_repos(matches, repos.clone(), logger)?;
+    let client = build_es_client(matches);
+    for repo in repos {
+        let logger = logger.new(o!("repo" => repo.repo_info().name.to_owned()));
+        if let Err(e) = index_ownership(matches, &repo, &client, &logger) {
+            error!(logger, "error analyzing ownership"; "error" => e.display_chain().to_string());
+        }
+    }
+    Ok(())
+}
+
+fn index_ownership(
+    matches: &clap::ArgMatches,
+    repo: &Repo,
+    client: &codealong_elk::Client,
+    logger: &Logger,
+) -> Result<()> {
+    info!(logger, "Analyzing ownership");
+    let analyzer = RepoAnalyzer::from_repo(repo, logger)?;
+    for ownership in analyzer.analyze_ownership(matches.value_of("ref"))? {
+        client.index(ownership)?;
+    }
+    Ok(())
+}
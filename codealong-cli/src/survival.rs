@@ -0,0 +1,53 @@
+use chrono::Duration;
+use error_chain::ChainedError;
+use slog::Logger;
+
+use codealong::{LineSurvivalOpts, Repo, RepoAnalyzer};
+
+use crate::analyze::build_repos;
+use crate::build_workspace::build_workspace;
+use crate::error::Result;
+use crate::initialize_repos::initialize_repos;
+use crate::utils::build_es_client;
+
+pub fn survival(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let workspace = build_workspace(matches, logger)?;
+    let repos = build_repos(&workspace, matches);
+    initialize_repos(matches, repos.clone(), logger)?;
+    let client = build_es_client(matches);
+    let opts = line_survival_opts_from_args(matches)?;
+    for repo in repos {
+        let logger = logger.new(o!("repo" => repo.repo_info().name.to_owned()));
+        if let Err(e) = index_line_survival(matches, &repo, &opts, &client, &logger) {
+            error!(logger, "error analyzing line survival"; "error" => e.display_chain().to_string());
+        }
+    }
+    Ok(())
+}
+
+fn index_line_survival(
+    matches: &clap::ArgMatches,
+    repo: &Repo,
+    opts: &LineSurvivalOpts,
+    client: &codealong_elk::Client,
+    logger: &Logger,
+) -> Result<()> {
+    info!(logger, "Analyzing line survival");
+    let analyzer = RepoAnalyzer::from_repo(repo, logger)?;
+    for line_survival in analyzer.analyze_line_survival(matches.value_of("ref"), opts)? {
+        client.index(line_survival)?;
+    }
+    Ok(())
+}
+
+fn line_survival_opts_from_args(matches: &clap::ArgMatches) -> Result<LineSurvivalOpts> {
+    let mut horizons = Vec::new();
+    for horizon in matches.values_of("horizon").unwrap() {
+        let horizon = humantime::parse_duration(horizon)?;
+        horizons.push(Duration::seconds(horizon.as_secs() as i64));
+    }
+    Ok(LineSurvivalOpts {
+        horizons,
+        ..LineSurvivalOpts::default()
+    })
+}
@@ -6,6 +6,37 @@ use crate::event::Event;
 use crate::error::Result;
 use reqwest;
 
+#[derive(Serialize)]
+struct PartialUpdate<T> {
+    doc: T,
+}
+
+#[derive(Serialize)]
+struct ScriptedUpdate<T> {
+    script: Script<T>,
+}
+
+#[derive(Serialize)]
+struct Script<T> {
+    source: &'static str,
+    lang: &'static str,
+    params: T,
+}
+
+const APPEND_SCRIPT: &str = "
+for (entry in params.entrySet()) {
+    def current = ctx._source[entry.getKey()];
+    if (entry.getValue() instanceof List && current instanceof List) {
+        for (value in entry.getValue()) {
+            if (!current.contains(value)) {
+                current.add(value);
+            }
+        }
+    } else {
+        ctx._source[entry.getKey()] = entry.getValue();
+    }
+}";
+
 pub struct Client {
     url: String,
 }
@@ -32,8 +63,52 @@ impl Client {
         let event = Event::new(event);
         let client = reqwest::Client::new();
         let index = get_es_index(event.timestamp());
-        let url = format!("{}/{}/_doc/{}", self.url, index, event.id());
-        Ok(client.put(&url).json(&event).send()?)
+        let url = self.doc_url(&index, &event.id(), &[])?;
+        Ok(client.put(url).json(&event).send()?)
+    }
+
+    /// Updates the fields of an already indexed event with those of `event`,
+    /// leaving the other fields as they are.
+    pub fn update<T: codealong::Event + serde::Serialize>(
+        &self,
+        event: T,
+    ) -> Result<reqwest::Response> {
+        let client = reqwest::Client::new();
+        let index = get_es_index(event.timestamp());
+        let url = self.doc_url(&index, &event.id(), &["_update"])?;
+        Ok(client
+            .post(url)
+            .json(&PartialUpdate { doc: event })
+            .send()?)
+    }
+
+    /// Same as `update`, but array fields of `event` are appended to the
+    /// existing arrays, skipping values which are already present.
+    pub fn append<T: codealong::Event + serde::Serialize>(
+        &self,
+        event: T,
+    ) -> Result<reqwest::Response> {
+        let client = reqwest::Client::new();
+        let index = get_es_index(event.timestamp());
+        let url = self.doc_url(&index, &event.id(), &["_update"])?;
+        let script = Script {
+            source: APPEND_SCRIPT,
+            lang: "painless",
+            params: event,
+        };
+        Ok(client.post(url).json(&ScriptedUpdate { script }).send()?)
+    }
+
+    /// The URL of the document `id` within `index`, followed by `suffix`.
+    /// Ids are escaped, as they may contain e.g. paths.
+    fn doc_url(&self, index: &str, id: &str, suffix: &[&str]) -> Result<reqwest::Url> {
+        let mut url = reqwest::Url::parse(&self.url)?;
+        url.path_segments_mut()
+            .map_err(|_| "invalid elasticsearch url")?
+            .pop_if_empty()
+            .extend(&[index, "_doc", id])
+            .extend(suffix);
+        Ok(url)
     }
 
     pub fn health(&self) -> Result<reqwest::Response> {
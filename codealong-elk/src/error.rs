@@ -4,5 +4,6 @@ use reqwest;
 error_chain! {
     foreign_links {
         ES(reqwest::Error);
+        Url(reqwest::UrlError);
     }
 }
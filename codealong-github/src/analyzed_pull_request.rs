@@ -4,7 +4,7 @@ use std::borrow::Cow;
 use std::collections::HashSet;
 use std::iter::FromIterator;
 
-use codealong::{AnalyzedDiff, Event, Contributor};
+use codealong::{AnalyzedDiff, ChangeOwners, Contributor, Event};
 
 use crate::pull_request::PullRequest;
 
@@ -21,6 +21,20 @@ pub struct AnalyzedPullRequest {
     pub diff: Option<AnalyzedDiff>,
 
     pub time_to_resolve: Option<i64>,
+
+    /// The commits merged through this pull request.
+    #[serde(default)]
+    pub commits: Vec<String>,
+
+    /// The owners of the changed files according to the `CODEOWNERS` file
+    /// of the base.
+    #[serde(default)]
+    pub owners: Vec<String>,
+
+    /// The number of changed files owned by others than the author and
+    /// their teams.
+    #[serde(default)]
+    pub cross_team_changes: usize,
 }
 
 impl AnalyzedPullRequest {
@@ -28,6 +42,7 @@ impl AnalyzedPullRequest {
         pr: PullRequest,
         diff: Option<AnalyzedDiff>,
         normalized_author: Contributor,
+        commits: Vec<String>,
     ) -> AnalyzedPullRequest {
         AnalyzedPullRequest {
             timestamp: pr.merged_at.unwrap_or(pr.updated_at),
@@ -37,9 +52,17 @@ impl AnalyzedPullRequest {
                 .merged_at
                 .as_ref()
                 .map(|ma| (ma.clone() - pr.created_at.clone()).num_seconds()),
+            commits,
             pr,
+            owners: Vec::new(),
+            cross_team_changes: 0,
         }
     }
+
+    pub fn set_change_owners(&mut self, change_owners: ChangeOwners) {
+        self.owners = change_owners.owners;
+        self.cross_team_changes = change_owners.cross_team_changes;
+    }
 }
 
 impl Event for AnalyzedPullRequest {
@@ -56,10 +79,14 @@ impl Event for AnalyzedPullRequest {
     }
 
     fn tags(&self) -> HashSet<String> {
-        if let Some(ref diff) = self.diff {
+        let mut tags = if let Some(ref diff) = self.diff {
             HashSet::from_iter(diff.tag_stats.keys().map(|s| s.to_owned()))
         } else {
             HashSet::new()
+        };
+        if self.cross_team_changes > 0 {
+            tags.insert("cross_team".to_owned());
         }
+        tags
     }
 }
@@ -155,6 +155,7 @@ fn build_repo_entries(
         },
         path: Some(format!("{}.git", repo.full_name)),
         ignore: false,
+        projects: Vec::new(),
     });
     Ok(res.collect())
 }
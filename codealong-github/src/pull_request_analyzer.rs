@@ -1,7 +1,10 @@
 use git2::{Oid, Repository};
 use slog::Logger;
 
-use codealong::{with_authentication, DiffAnalyzer, RepoInfo, WorkingConfig};
+use codealong::{
+    with_authentication, ChangeOwners, CodeOwners, Contributor, DiffAnalyzer, RepoInfo,
+    WorkingConfig,
+};
 
 use crate::analyzed_pull_request::AnalyzedPullRequest;
 use crate::error::{Error, Result};
@@ -53,17 +56,65 @@ impl<'a> PullRequestAnalyzer<'a> {
             })
             .ok();
 
+        let commits = self.merged_commits().unwrap_or_default();
+
         let normalized_author = self
             .config
             .contributor_for_github_login(&self.pr.user.login);
+        let change_owners = match self.change_owners(&normalized_author) {
+            Ok(change_owners) => change_owners,
+            Err(e) => {
+                let error = e.to_string();
+                warn!(self.logger, "Could not determine change owners"; "error" => error);
+                None
+            }
+        };
         debug!(self.logger, "Done analyzing");
-        Ok(AnalyzedPullRequest::new(self.pr, diff, normalized_author))
+        let mut result = AnalyzedPullRequest::new(self.pr, diff, normalized_author, commits);
+        if let Some(change_owners) = change_owners {
+            result.set_change_owners(change_owners);
+        }
+        Ok(result)
     }
 
     pub fn is_author_known(&self) -> bool {
         self.config.is_github_login_known(&self.pr.user.login)
     }
 
+    /// The owners of the files changed by the pull request according to the
+    /// `CODEOWNERS` file of its base, if it has one.
+    fn change_owners(&self, author: &Contributor) -> Result<Option<ChangeOwners>> {
+        let base = self.repo.find_commit(Oid::from_str(&self.pr.base.sha)?)?;
+        let head = self.repo.find_commit(Oid::from_str(&self.pr.head.sha)?)?;
+        let code_owners = match CodeOwners::from_tree(self.repo, &base.tree()?)? {
+            Some(code_owners) => code_owners,
+            None => return Ok(None),
+        };
+        let paths =
+            DiffAnalyzer::new(self.repo, &head, Some(&base), self.config).changed_paths()?;
+        Ok(Some(code_owners.change_owners(paths, author)))
+    }
+
+    /// The commits of a merged pull request along with the commit it was
+    /// merged as.
+    fn merged_commits(&self) -> Result<Vec<String>> {
+        if self.pr.merged_at.is_none() {
+            return Ok(Vec::new());
+        }
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.push(Oid::from_str(&self.pr.head.sha)?)?;
+        revwalk.hide(Oid::from_str(&self.pr.base.sha)?)?;
+        let mut commits = revwalk
+            .map(|oid| Ok(oid?.to_string()))
+            .collect::<Result<Vec<_>>>()?;
+        if let Some(ref merge_commit_sha) = self.pr.merge_commit_sha {
+            if !commits.contains(merge_commit_sha) {
+                commits.push(merge_commit_sha.clone());
+            }
+        }
+        Ok(commits)
+    }
+
     fn fetch_remote(&self, reference: &Ref) -> Result<()> {
         if let Some(ref repo) = reference.repo {
             let git_config = git2::Config::open_default()?;
@@ -50,7 +50,7 @@ impl<'client> PullRequestsAnalyzer<'client> {
     }
 
     pub fn guess_len(&self, opts: AnalyzeOpts) -> Result<usize> {
-        if opts.since.is_some() {
+        if opts.since.is_some() || opts.state.pull_requests_updated_at.is_some() {
             Ok(self.analyze(opts)?.count())
         } else {
             Ok(self
@@ -61,12 +61,12 @@ impl<'client> PullRequestsAnalyzer<'client> {
     }
 
     pub fn from_repo(repo: &Repo, client: &'client Client, logger: &Logger) -> Result<Self> {
-        Ok(Self::new(repo.repository()?, repo.config(), client, logger))
+        Ok(Self::new(repo.repository()?, repo.config()?, client, logger))
     }
 
     fn build_cursor(&self, _opts: AnalyzeOpts) -> Cursor<PullRequest> {
         let url = format!(
-            "https://api.github.com/repos/{}/pulls?state=all",
+            "https://api.github.com/repos/{}/pulls?state=all&sort=updated&direction=desc",
             self.config.repo.github_name.as_ref().unwrap()
         );
         Cursor::new(&self.client, &url, &self.logger)
@@ -97,6 +97,12 @@ impl<'client> Iterator for PullRequestsCursor<'client> {
                         }
                     }
 
+                    if let Some(ref updated_at) = self.opts.state.pull_requests_updated_at {
+                        if updated_at >= &pr.updated_at {
+                            break None;
+                        }
+                    }
+
                     if !self.opts.ignore_unknown_authors
                         || self.config.is_github_login_known(&pr.user.login)
                     {
@@ -0,0 +1,156 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+use git2::{Oid, Repository};
+use tempfile::NamedTempFile;
+
+use crate::analyzed_diff::AnalyzedDiff;
+use crate::binary_stats::BinaryStats;
+use crate::config_context::ConfigContext;
+use crate::error::*;
+use crate::hunk_analyzer::calculate_impact;
+use crate::identity::Identity;
+use crate::line_analyzer::CHURN_WINDOW;
+use crate::submodule_update::SubmoduleUpdate;
+use crate::szz::BlamedLines;
+use crate::work_stats::WorkStats;
+use crate::working_config::WorkingConfig;
+
+/// Bump whenever the way lines are classified changes so that existing
+/// caches are invalidated.
+const CLASSIFICATION_VERSION: u64 = 3;
+
+/// An on-disk cache of the raw line classification of each analyzed commit.
+///
+/// Classifying lines requires running blame, which dominates the cost of an
+/// analysis. Tags, weights and impact on the other hand only depend on the
+/// config, so when those are all that changed, analyzed commits can be
+/// rebuilt from the cache.
+///
+/// Each commit is stored in a file of its own, so that analysis threads and
+/// concurrent runs can write entries without coordinating.
+pub struct AnalysisCache {
+    dir: PathBuf,
+}
+
+/// The raw classification of every file changed by a commit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedCommit {
+    /// Hash of the config settings the classification depends on.
+    pub classification_hash: u64,
+    pub files: Vec<CachedFile>,
+
+    /// The lines blamed for the bug fixed by the commit, if it was analyzed
+    /// as a bug fix with bug fixes being blamed.
+    #[serde(default)]
+    pub blamed_lines: Option<Vec<BlamedLines>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedFile {
+    /// The path of the file. Paths which are not valid UTF-8 are stored
+    /// lossily.
+    pub path: String,
+
+    /// Whether the lines of this file were classified. Lines of ignored files
+    /// are not, in which case `hunks` only holds empty stats.
+    pub classified: bool,
+
+    /// The stats of each hunk, without impact.
+    pub hunks: Vec<WorkStats>,
+
+    /// Set if the file is a submodule, whose lines are not classified.
+    #[serde(default)]
+    pub submodule: Option<SubmoduleUpdate>,
+
+    /// Set if the file is binary, whose lines are not classified either.
+    #[serde(default)]
+    pub binary: Option<BinaryStats>,
+}
+
+impl AnalysisCache {
+    pub fn new(dir: PathBuf) -> AnalysisCache {
+        AnalysisCache { dir }
+    }
+
+    /// The cache stored within the git directory of `repo`.
+    pub fn for_repository(repo: &Repository) -> AnalysisCache {
+        Self::new(repo.path().join("codealong").join("cache"))
+    }
+
+    pub fn get(&self, oid: Oid) -> Option<CachedCommit> {
+        let file = File::open(self.path(oid)).ok()?;
+        serde_yaml::from_reader(file).ok()
+    }
+
+    pub fn put(&self, oid: Oid, cached_commit: &CachedCommit) -> Result<()> {
+        let path = self.path(oid);
+        fs::create_dir_all(path.parent().unwrap())?;
+        // Write to a temporary file first so that concurrent readers never
+        // see a partially written entry.
+        let mut tmp_file = NamedTempFile::new_in(path.parent().unwrap())?;
+        serde_yaml::to_writer(tmp_file.as_file_mut(), cached_commit)?;
+        tmp_file.persist(path).map_err(|e| e.error)?;
+        Ok(())
+    }
+
+    fn path(&self, oid: Oid) -> PathBuf {
+        let id = oid.to_string();
+        self.dir.join(&id[..2]).join(format!("{}.yml", &id[2..]))
+    }
+}
+
+impl CachedCommit {
+    /// Rebuilds the analyzed diff of a commit by `author` under `config`.
+    ///
+    /// Returns `None` if the cached classification cannot be used, either
+    /// because it depends on settings which changed or because a file which
+    /// was ignored when it was cached no longer is.
+    pub fn rebuild(&self, config: &WorkingConfig, author: &Identity) -> Option<AnalyzedDiff> {
+        if self.classification_hash != classification_hash(config) {
+            return None;
+        }
+        let author_config = config.config_for_identity(author);
+        let mut result = AnalyzedDiff::empty();
+        for file in &self.files {
+            if let Some(ref submodule) = file.submodule {
+                result.add_submodule(submodule.clone());
+                continue;
+            }
+            let file_config = config.config_for_file(&file.path);
+            let ignored = file_config.as_ref().map(|c| c.ignore()).unwrap_or(false);
+            if !ignored && !file.classified {
+                return None;
+            }
+            match file.binary {
+                Some(binary) if !ignored => result.add_binary(binary, &file.path),
+                _ => {}
+            }
+            let config_context = ConfigContext::new(file_config.as_ref(), author_config);
+            let module = config.module_for_path(file.path.as_bytes());
+            for hunk in &file.hunks {
+                let mut stats = if ignored { WorkStats::empty() } else { *hunk };
+                stats.impact = calculate_impact(&stats, config_context.weight());
+                result.add_stats(stats, config_context.tags());
+                if let Some(ref module) = module {
+                    result.add_module_stats(stats, module);
+                }
+            }
+        }
+        Some(result)
+    }
+}
+
+/// Hashes the settings which line classification depends on: the window
+/// within which changes count as churn and the `churn_cutoff` blame is limited
+/// to. The hash may change between Rust releases, which merely invalidates the
+/// cache.
+pub fn classification_hash(config: &WorkingConfig) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    CLASSIFICATION_VERSION.hash(&mut hasher);
+    CHURN_WINDOW.hash(&mut hasher);
+    config.churn_cutoff().hash(&mut hasher);
+    hasher.finish()
+}
@@ -0,0 +1,169 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::*;
+use chrono::DateTime;
+
+use crate::error::*;
+
+/// Records how far each repository in a workspace has been analyzed, so that
+/// subsequent runs only need to analyze what is new.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalysisState {
+    #[serde(default)]
+    pub repos: BTreeMap<String, RepoState>,
+}
+
+/// The analysis state of a single repository.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RepoState {
+    /// The last analyzed commit for each analyzed ref.
+    #[serde(default)]
+    pub refs: BTreeMap<String, String>,
+
+    /// The `updated_at` of the most recently updated pull request analyzed.
+    #[serde(default)]
+    pub pull_requests_updated_at: Option<DateTime<Utc>>,
+
+    /// The release tags which have been analyzed.
+    #[serde(default)]
+    pub releases: BTreeSet<String>,
+
+    /// The pull request each commit id belongs to according to the commit
+    /// lists of the pull requests analyzed by the last run, for the commits
+    /// which had not been analyzed yet. Replaced by each run analyzing pull
+    /// requests, as the commits of pull requests which are merged later are
+    /// listed again when they are re-analyzed.
+    #[serde(default)]
+    pub pull_request_commits: Option<BTreeMap<String, u64>>,
+}
+
+impl AnalysisState {
+    pub const DEFAULT_PATH: &'static str = "state.yml";
+
+    /// Reads the state stored in the workspace at `dir`, or an empty state if
+    /// nothing has been analyzed yet.
+    pub fn from_dir(dir: &Path) -> Result<Self> {
+        let path = Self::path(dir);
+        if path.exists() {
+            Self::from_path(&path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        Ok(serde_yaml::from_reader::<_, AnalysisState>(file)?)
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let file = File::create(Self::path(dir))?;
+        Ok(serde_yaml::to_writer(file, self)?)
+    }
+
+    pub fn repo(&self, name: &str) -> Option<&RepoState> {
+        self.repos.get(name)
+    }
+
+    /// Records the progress in `other`, which takes precedence for any ref
+    /// analyzed in both.
+    pub fn merge(&mut self, other: AnalysisState) {
+        for (name, repo_state) in other.repos {
+            self.repos
+                .entry(name)
+                .or_insert_with(RepoState::default)
+                .merge(repo_state);
+        }
+    }
+
+    fn path(dir: &Path) -> PathBuf {
+        dir.join(Self::DEFAULT_PATH)
+    }
+}
+
+impl RepoState {
+    pub fn merge(&mut self, other: RepoState) {
+        self.refs.extend(other.refs);
+        self.releases.extend(other.releases);
+        if other.pull_request_commits.is_some() {
+            self.pull_request_commits = other.pull_request_commits;
+        }
+        self.pull_requests_updated_at = self
+            .pull_requests_updated_at
+            .max(other.pull_requests_updated_at);
+    }
+}
+
+impl Default for AnalysisState {
+    fn default() -> AnalysisState {
+        AnalysisState {
+            repos: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for RepoState {
+    fn default() -> RepoState {
+        RepoState {
+            refs: BTreeMap::new(),
+            pull_requests_updated_at: None,
+            releases: BTreeSet::new(),
+            pull_request_commits: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_save_and_merge() -> Result<()> {
+        let dir = TempDir::new("codealong")?;
+        let mut state = AnalysisState::from_dir(dir.path())?;
+        assert!(state.repos.is_empty());
+
+        let mut repo_state = RepoState::default();
+        repo_state
+            .refs
+            .insert("refs/heads/master".to_owned(), "abc".to_owned());
+        repo_state.pull_requests_updated_at = Some(Utc.ymd(2019, 1, 1).and_hms(0, 0, 0));
+        let mut pull_request_commits = BTreeMap::new();
+        pull_request_commits.insert("abc".to_owned(), 1);
+        repo_state.pull_request_commits = Some(pull_request_commits);
+        state.repos.insert("simple".to_owned(), repo_state);
+        state.save(dir.path())?;
+
+        let mut other = AnalysisState::default();
+        let mut repo_state = RepoState::default();
+        repo_state
+            .refs
+            .insert("refs/heads/master".to_owned(), "def".to_owned());
+        other.repos.insert("simple".to_owned(), repo_state);
+
+        let mut state = AnalysisState::from_dir(dir.path())?;
+        state.merge(other);
+        let repo_state = state.repo("simple").unwrap();
+        assert_eq!(repo_state.refs["refs/heads/master"], "def");
+        assert_eq!(
+            repo_state.pull_requests_updated_at,
+            Some(Utc.ymd(2019, 1, 1).and_hms(0, 0, 0))
+        );
+        assert_eq!(repo_state.pull_request_commits.as_ref().unwrap()["abc"], 1);
+
+        // the pull request commits of the latest run replace earlier ones
+        let mut other = AnalysisState::default();
+        let mut repo_state = RepoState::default();
+        repo_state.pull_request_commits = Some(BTreeMap::new());
+        other.repos.insert("simple".to_owned(), repo_state);
+        state.merge(other);
+        assert_eq!(
+            state.repo("simple").unwrap().pull_request_commits,
+            Some(BTreeMap::new())
+        );
+        Ok(())
+    }
+}
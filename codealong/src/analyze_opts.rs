@@ -1,10 +1,40 @@
 use chrono::prelude::*;
 use chrono::DateTime;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::analysis_state::RepoState;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AnalyzeOpts {
     pub ignore_unknown_authors: bool,
     pub since: Option<DateTime<Utc>>,
+
+    /// Analyze each commit using the `.codealong.yml` committed in that
+    /// commit's tree instead of the one at the analyzed ref.
+    #[serde(default)]
+    pub historical_config: bool,
+
+    /// Progress of previous runs. Commits reachable from the recorded refs and
+    /// pull requests not updated since are skipped.
+    #[serde(default)]
+    pub state: RepoState,
+
+    /// Reuse the line classification cached by previous runs when only tags
+    /// or weights changed, and cache it otherwise.
+    #[serde(default)]
+    pub cache: bool,
+
+    /// Blame the lines changed by bug fixes to find the commits which
+    /// introduced the bugs.
+    #[serde(default)]
+    pub blame_bug_fixes: bool,
+
+    /// Local clones of the repositories submodules may point to, keyed by
+    /// repo name, e.g. `org/repo`. The commits brought in by updates of
+    /// these submodules are resolved.
+    #[serde(default)]
+    pub submodule_repos: BTreeMap<String, PathBuf>,
 }
 
 impl Default for AnalyzeOpts {
@@ -12,6 +42,11 @@ impl Default for AnalyzeOpts {
         AnalyzeOpts {
             ignore_unknown_authors: false,
             since: None,
+            historical_config: false,
+            state: RepoState::default(),
+            cache: false,
+            blame_bug_fixes: false,
+            submodule_repos: BTreeMap::new(),
         }
     }
 }
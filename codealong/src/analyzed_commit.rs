@@ -1,9 +1,11 @@
 use crate::analyzed_diff::AnalyzedDiff;
+use crate::code_owners::ChangeOwners;
+use crate::config::WorkingHours;
 use crate::contributor::Contributor;
 use crate::event::Event;
 use crate::identity::Identity;
 use crate::repo_info::PartialRepoInfo;
-use crate::utils::convert_time;
+use crate::utils::{convert_local_time, convert_time};
 
 use chrono::prelude::*;
 use chrono::DateTime;
@@ -20,31 +22,170 @@ pub struct AnalyzedCommit {
     pub summary: Option<String>,
     pub author: Identity,
     pub authored_at: DateTime<Utc>,
+
+    /// The offset from UTC the commit was authored in.
+    #[serde(default)]
+    pub author_utc_offset_minutes: i32,
+
+    /// The hour of the day and weekday the commit was authored at, in the
+    /// author's local time.
+    #[serde(default)]
+    pub local_hour: u32,
+    #[serde(default)]
+    pub local_weekday: String,
+
+    /// Whether the commit was authored on a day off, or outside of the
+    /// author's working hours, respectively. Days off are off hours too.
+    #[serde(default)]
+    pub is_weekend: bool,
+    #[serde(default)]
+    pub is_off_hours: bool,
+
     pub normalized_author: Option<Contributor>,
+
+    /// Whether the author is one of the contributors of the config the
+    /// commit was analyzed with. Not indexed.
+    #[serde(skip)]
+    pub is_author_known: bool,
+
     pub committer: Identity,
     pub committed_at: DateTime<Utc>,
     pub normalized_committer: Option<Contributor>,
     pub repo: Option<PartialRepoInfo>,
+
+    /// The project of a monorepo this part of the commit belongs to. Commits
+    /// changing several projects are split into one part per project.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+
     pub github_url: Option<String>,
+
+    /// The analyzed branches this commit is reachable from.
+    #[serde(default)]
+    pub branches: Vec<String>,
+
+    #[serde(default)]
+    pub on_default_branch: bool,
+
+    /// When the first release containing this commit was released.
+    #[serde(default)]
+    pub released_at: Option<DateTime<Utc>>,
+
+    /// The time from authoring this commit until it was released.
+    #[serde(default)]
+    pub lead_time_seconds: Option<i64>,
+
+    /// The pull request this commit was merged through.
+    #[serde(default)]
+    pub pull_request_number: Option<u64>,
+
+    /// Whether this commit went through a pull request rather than being
+    /// pushed directly.
+    #[serde(default)]
+    pub reviewed: bool,
+
+    /// The commit this commit reverts.
+    #[serde(default)]
+    pub reverts: Option<String>,
+
+    /// The commit reverting this commit, whose work should not count.
+    #[serde(default)]
+    pub reverted_by: Option<String>,
+
+    /// Whether this commit fixes a bug according to its message.
+    #[serde(default)]
+    pub bug_fix: bool,
+
+    /// The commits which introduced the bug fixed by this commit.
+    #[serde(default)]
+    pub bug_introducing_commits: Vec<String>,
+
+    /// Whether a later commit fixed a bug introduced by this commit.
+    #[serde(default)]
+    pub bug_introducing: bool,
+
+    /// The commits fixing bugs introduced by this commit.
+    #[serde(default)]
+    pub fixed_by: Vec<String>,
+
+    /// The owners of the changed files according to `CODEOWNERS`.
+    #[serde(default)]
+    pub owners: Vec<String>,
+
+    /// The number of changed files owned by others than the author and
+    /// their teams.
+    #[serde(default)]
+    pub cross_team_changes: usize,
 }
 
 impl AnalyzedCommit {
     pub fn new(commit: &Commit) -> AnalyzedCommit {
+        let local_authored_at = convert_local_time(&commit.author().when());
         AnalyzedCommit {
             id: commit.id().to_string(),
             diff: AnalyzedDiff::empty(),
             summary: commit.summary().map(|s| s.to_string()),
             author: Identity::from(commit.author()),
             authored_at: convert_time(&commit.author().when()),
+            author_utc_offset_minutes: commit.author().when().offset_minutes(),
+            local_hour: local_authored_at.hour(),
+            local_weekday: local_authored_at.format("%a").to_string(),
+            is_weekend: false,
+            is_off_hours: false,
             normalized_author: None,
+            is_author_known: false,
             committer: Identity::from(commit.committer()),
             committed_at: convert_time(&commit.committer().when()),
             normalized_committer: None,
             repo: None,
+            project: None,
             github_url: None,
+            branches: Vec::new(),
+            on_default_branch: false,
+            released_at: None,
+            lead_time_seconds: None,
+            pull_request_number: None,
+            reviewed: false,
+            reverts: None,
+            reverted_by: None,
+            bug_fix: false,
+            bug_introducing_commits: Vec::new(),
+            bug_introducing: false,
+            fixed_by: Vec::new(),
+            owners: Vec::new(),
+            cross_team_changes: 0,
         }
     }
 
+    pub fn set_released_at(&mut self, released_at: DateTime<Utc>) {
+        self.released_at = Some(released_at);
+        self.lead_time_seconds = Some((released_at - self.authored_at).num_seconds());
+    }
+
+    pub fn set_pull_request_number(&mut self, pull_request_number: Option<u64>) {
+        self.pull_request_number = pull_request_number;
+        self.reviewed = pull_request_number.is_some();
+    }
+
+    /// Flags the commit if it was authored outside of `working_hours`.
+    pub fn set_working_hours(&mut self, working_hours: &WorkingHours) {
+        let local_authored_at = self.local_authored_at();
+        self.is_weekend = !working_hours.is_working_day(local_authored_at.weekday());
+        self.is_off_hours =
+            self.is_weekend || !working_hours.is_working_hour(local_authored_at.hour());
+    }
+
+    /// When the commit was authored, in the author's time zone.
+    pub fn local_authored_at(&self) -> DateTime<FixedOffset> {
+        self.authored_at
+            .with_timezone(&FixedOffset::east(self.author_utc_offset_minutes * 60))
+    }
+
+    pub fn set_change_owners(&mut self, change_owners: ChangeOwners) {
+        self.owners = change_owners.owners;
+        self.cross_team_changes = change_owners.cross_team_changes;
+    }
+
     pub fn merge_diff(&mut self, diff: &AnalyzedDiff) {
         self.diff = &self.diff + diff;
     }
@@ -60,10 +201,18 @@ impl Event for AnalyzedCommit {
     }
 
     fn id(&self) -> Cow<str> {
-        Cow::Borrowed(&self.id)
+        commit_event_id(&self.id, self.project.as_ref())
     }
 
     fn tags(&self) -> HashSet<String> {
         HashSet::from_iter(self.diff.tag_stats.keys().map(|s| s.to_owned()))
     }
 }
+
+/// The id of the event of commit `id`, or of its part belonging to `project`.
+pub(crate) fn commit_event_id<'a>(id: &'a str, project: Option<&String>) -> Cow<'a, str> {
+    match project {
+        Some(project) => Cow::Owned(format!("{}:{}", id, project)),
+        None => Cow::Borrowed(id),
+    }
+}
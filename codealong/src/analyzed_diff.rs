@@ -1,3 +1,5 @@
+use crate::binary_stats::{binary_type, BinaryStats};
+use crate::submodule_update::SubmoduleUpdate;
 use crate::work_stats::WorkStats;
 use std::collections::HashMap;
 use std::ops::{Add, AddAssign};
@@ -6,6 +8,18 @@ use std::ops::{Add, AddAssign};
 pub struct AnalyzedDiff {
     pub stats: WorkStats,
     pub tag_stats: HashMap<String, WorkStats>,
+
+    /// The stats of the files of each module.
+    #[serde(default)]
+    pub module_stats: HashMap<String, WorkStats>,
+
+    /// The submodules pointed to other commits, whose lines are not counted.
+    #[serde(default)]
+    pub submodules: Vec<SubmoduleUpdate>,
+
+    /// The binary files changed, whose lines are not counted.
+    #[serde(default)]
+    pub binary: BinaryStats,
 }
 
 impl AnalyzedDiff {
@@ -25,6 +39,33 @@ impl AnalyzedDiff {
             }
         }
     }
+
+    /// Records the update of a submodule, tagging the diff `submodule`.
+    pub fn add_submodule(&mut self, submodule: SubmoduleUpdate) {
+        self.tag_stats
+            .entry("submodule".to_owned())
+            .or_insert_with(WorkStats::empty);
+        self.submodules.push(submodule);
+    }
+
+    /// Records the change of the binary file at `path`, tagging the diff with
+    /// the type of the file if known.
+    pub fn add_binary(&mut self, stats: BinaryStats, path: &str) {
+        if let Some(binary_type) = binary_type(path) {
+            self.tag_stats
+                .entry(binary_type.to_owned())
+                .or_insert_with(WorkStats::empty);
+        }
+        self.binary += stats;
+    }
+
+    /// Adds the stats to `module`.
+    pub fn add_module_stats(&mut self, stats: WorkStats, module: &str) {
+        *self
+            .module_stats
+            .entry(module.to_string())
+            .or_insert_with(WorkStats::empty) += stats;
+    }
 }
 
 impl Default for AnalyzedDiff {
@@ -32,6 +73,9 @@ impl Default for AnalyzedDiff {
         AnalyzedDiff {
             stats: WorkStats::empty(),
             tag_stats: HashMap::new(),
+            module_stats: HashMap::new(),
+            submodules: Vec::new(),
+            binary: BinaryStats::empty(),
         }
     }
 }
@@ -67,6 +111,14 @@ impl<'a, 'b> Add<&'b AnalyzedDiff> for &'a AnalyzedDiff {
         AnalyzedDiff {
             stats: self.stats + other.stats,
             tag_stats: merge_tag_stats(&self.tag_stats, &other.tag_stats),
+            module_stats: merge_tag_stats(&self.module_stats, &other.module_stats),
+            submodules: self
+                .submodules
+                .iter()
+                .chain(other.submodules.iter())
+                .cloned()
+                .collect(),
+            binary: self.binary + other.binary,
         }
     }
 }
@@ -81,6 +133,9 @@ impl<'a> AddAssign<&'a AnalyzedDiff> for AnalyzedDiff {
     fn add_assign(&mut self, other: &'a AnalyzedDiff) {
         self.stats += other.stats;
         self.tag_stats = merge_tag_stats(&self.tag_stats, &other.tag_stats);
+        self.module_stats = merge_tag_stats(&self.module_stats, &other.module_stats);
+        self.submodules.extend(other.submodules.iter().cloned());
+        self.binary += other.binary;
     }
 }
 
@@ -107,6 +162,9 @@ mod tests {
         let diff = AnalyzedDiff {
             stats: WorkStats::new_work(),
             tag_stats,
+            module_stats: HashMap::new(),
+            submodules: Vec::new(),
+            binary: BinaryStats::empty(),
         };
 
         let mut tag_stats2 = HashMap::new();
@@ -114,6 +172,9 @@ mod tests {
         let diff2 = AnalyzedDiff {
             stats: WorkStats::new_work(),
             tag_stats: tag_stats2,
+            module_stats: HashMap::new(),
+            submodules: Vec::new(),
+            binary: BinaryStats::empty(),
         };
 
         let result = diff + diff2;
@@ -131,5 +192,10 @@ mod tests {
         assert_eq!(diff.tag_stats.len(), 2);
         assert_eq!(diff.tag_stats.get("ruby").unwrap().legacy_refactor, 1);
         assert_eq!(diff.tag_stats.get("rspec").unwrap().legacy_refactor, 1);
+
+        diff.add_module_stats(WorkStats::new_work(), "api");
+        diff.add_module_stats(WorkStats::churn(), "api");
+        assert_eq!(diff.module_stats["api"].new_work, 1);
+        assert_eq!(diff.module_stats["api"].churn, 1);
     }
 }
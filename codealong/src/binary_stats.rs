@@ -0,0 +1,109 @@
+use std::ops::{Add, AddAssign};
+
+use git2::{Delta, DiffDelta, DiffFile, Repository};
+
+/// Counts of the binary files added, modified and deleted by a diff. Binary
+/// files have no lines, so they do not count towards the work stats.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct BinaryStats {
+    pub added: u64,
+    pub modified: u64,
+    pub deleted: u64,
+
+    /// The change in the total size of the files, in bytes.
+    pub size_delta: i64,
+}
+
+impl BinaryStats {
+    pub fn empty() -> BinaryStats {
+        Default::default()
+    }
+
+    /// The stats of the binary file changed by `diff_delta`, from the sizes
+    /// the diff determined while detecting it as binary. Once one side is
+    /// found to be binary, the other is not loaded, so its size is read from
+    /// the object header instead.
+    pub fn for_delta(repo: &Repository, diff_delta: &DiffDelta) -> BinaryStats {
+        // the absent side of an addition or deletion has a zero id
+        let size = |file: DiffFile| {
+            if file.id().is_zero() {
+                0
+            } else if file.size() > 0 {
+                file.size() as i64
+            } else {
+                repo.odb()
+                    .and_then(|odb| odb.read_header(file.id()))
+                    .map(|(size, _)| size as i64)
+                    .unwrap_or(0)
+            }
+        };
+        let mut stats = BinaryStats {
+            size_delta: size(diff_delta.new_file()) - size(diff_delta.old_file()),
+            ..BinaryStats::empty()
+        };
+        match diff_delta.status() {
+            Delta::Added => stats.added = 1,
+            Delta::Deleted => stats.deleted = 1,
+            _ => stats.modified = 1,
+        }
+        stats
+    }
+}
+
+/// The type of a binary file by its extension, i.e. `image`, `font` or
+/// `archive`, which the diffs changing it are tagged with.
+pub fn binary_type(path: &str) -> Option<&'static str> {
+    const TYPES: [(&str, &[&str]); 3] = [
+        (
+            "image",
+            &[
+                "png", "jpg", "jpeg", "gif", "bmp", "ico", "webp", "tif", "tiff", "psd",
+            ],
+        ),
+        ("font", &["ttf", "otf", "woff", "woff2", "eot"]),
+        (
+            "archive",
+            &["zip", "tar", "gz", "tgz", "bz2", "xz", "7z", "rar", "jar"],
+        ),
+    ];
+    let name = path.rsplit('/').next()?;
+    let extension = &name[name.rfind('.')? + 1..];
+    TYPES
+        .iter()
+        .find(|(_, extensions)| extensions.iter().any(|e| e.eq_ignore_ascii_case(extension)))
+        .map(|(binary_type, _)| *binary_type)
+}
+
+impl Add for BinaryStats {
+    type Output = BinaryStats;
+
+    fn add(self, other: BinaryStats) -> BinaryStats {
+        BinaryStats {
+            added: self.added + other.added,
+            modified: self.modified + other.modified,
+            deleted: self.deleted + other.deleted,
+            size_delta: self.size_delta + other.size_delta,
+        }
+    }
+}
+
+impl AddAssign for BinaryStats {
+    fn add_assign(&mut self, other: BinaryStats) {
+        *self = *self + other;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_type() {
+        assert_eq!(binary_type("assets/logo.PNG"), Some("image"));
+        assert_eq!(binary_type("fonts/body.woff2"), Some("font"));
+        assert_eq!(binary_type("dist/release.tar.gz"), Some("archive"));
+        assert_eq!(binary_type("data.bin"), None);
+        assert_eq!(binary_type("png"), None);
+        assert_eq!(binary_type("v1.2/Makefile"), None);
+    }
+}
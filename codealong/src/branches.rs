@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+
+use git2::{Oid, ReferenceType, Repository, Sort};
+use slog::Logger;
+
+use crate::error::*;
+use crate::repo_info::RepoInfo;
+
+/// A ref selected for analysis.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalyzedRef {
+    /// The full name of the ref, e.g. `refs/remotes/origin/master`.
+    pub name: String,
+
+    /// The name of the branch without any remote, e.g. `master`.
+    pub branch: String,
+
+    pub oid: Oid,
+}
+
+/// The refs of a repository selected for analysis along with which of them
+/// each commit is reachable from.
+pub struct Branches {
+    refs: Vec<AnalyzedRef>,
+    default_ref: Option<String>,
+    reachable_from: Option<HashMap<Oid, RefSet>>,
+}
+
+impl Branches {
+    /// Resolves the refs and ref patterns (e.g. `refs/remotes/origin/*`) of
+    /// `repo_info`. If none are configured, the default branch is analyzed.
+    /// Falls back to `HEAD` if nothing matches.
+    pub fn resolve(repo: &Repository, repo_info: &RepoInfo, logger: &Logger) -> Result<Branches> {
+        let default_ref = default_branch(repo, repo_info);
+        let patterns = if repo_info.refs.is_empty() {
+            default_ref.iter().cloned().collect()
+        } else {
+            repo_info.refs.clone()
+        };
+        let mut refs: Vec<AnalyzedRef> = Vec::new();
+        for pattern in &patterns {
+            let mut matches = Vec::new();
+            if pattern.contains(|c| c == '*' || c == '?' || c == '[') {
+                for reference in repo.references_glob(pattern)? {
+                    let reference = reference?;
+                    // e.g. `refs/remotes/origin/HEAD`, whose target is
+                    // matched on its own
+                    if reference.kind() == Some(ReferenceType::Symbolic) {
+                        continue;
+                    }
+                    matches.push(reference);
+                }
+            } else if let Ok(reference) = repo.find_reference(pattern) {
+                matches.push(reference.resolve()?);
+            }
+            if matches.is_empty() {
+                warn!(logger, "Could not find reference: {}", pattern);
+            }
+            for reference in matches {
+                let name = match reference.name() {
+                    Some(name) => name.to_owned(),
+                    None => continue,
+                };
+                if refs.iter().any(|r| r.name == name) {
+                    continue;
+                }
+                refs.push(AnalyzedRef {
+                    branch: branch_name(&name),
+                    oid: reference.peel_to_commit()?.id(),
+                    name,
+                });
+            }
+        }
+        if refs.is_empty() {
+            warn!(logger, "No references to analyze, using HEAD");
+            if let Ok(head) = repo.head() {
+                let name = head.name().unwrap_or("HEAD").to_owned();
+                refs.push(AnalyzedRef {
+                    branch: branch_name(&name),
+                    name,
+                    oid: head.peel_to_commit()?.id(),
+                });
+            }
+        }
+        Ok(Branches {
+            refs,
+            default_ref,
+            reachable_from: None,
+        })
+    }
+
+    /// No refs, so that commits are not attributed to any branch.
+    pub(crate) fn empty() -> Branches {
+        Branches {
+            refs: Vec::new(),
+            default_ref: None,
+            reachable_from: None,
+        }
+    }
+
+    pub fn refs(&self) -> &[AnalyzedRef] {
+        &self.refs
+    }
+
+    /// Walks the history of the refs to record which refs each commit is
+    /// reachable from. Commits reachable from `hide` are skipped. This is
+    /// only needed when analyzing more than one ref.
+    ///
+    /// The history is walked once, children before parents, passing the refs
+    /// of each commit on to its parents.
+    pub fn compute_reachability(&mut self, repo: &Repository, hide: &[Oid]) -> Result<()> {
+        if self.refs.len() < 2 {
+            return Ok(());
+        }
+        let mut reachable_from: HashMap<Oid, RefSet> = HashMap::new();
+        let mut revwalk = repo.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL);
+        for (index, reference) in self.refs.iter().enumerate() {
+            reachable_from
+                .entry(reference.oid)
+                .or_insert_with(RefSet::default)
+                .insert(index);
+            revwalk.push(reference.oid)?;
+        }
+        for oid in hide {
+            revwalk.hide(*oid)?;
+        }
+        for oid in revwalk {
+            let oid = oid?;
+            let refs = reachable_from.get(&oid).cloned().unwrap_or_default();
+            for parent_id in repo.find_commit(oid)?.parent_ids() {
+                reachable_from
+                    .entry(parent_id)
+                    .or_insert_with(RefSet::default)
+                    .union(&refs);
+            }
+        }
+        self.reachable_from = Some(reachable_from);
+        Ok(())
+    }
+
+    /// The branches `oid` is reachable from and whether one of them is the
+    /// default branch.
+    pub fn branches_of(&self, oid: Oid) -> (Vec<String>, bool) {
+        let indexes: Vec<usize> = match self.reachable_from {
+            Some(ref reachable_from) => reachable_from
+                .get(&oid)
+                .map(|refs| refs.iter().collect())
+                .unwrap_or_default(),
+            None => (0..self.refs.len()).collect(),
+        };
+        let mut branches: Vec<String> = Vec::new();
+        let mut on_default_branch = false;
+        for index in indexes {
+            let reference = &self.refs[index];
+            if Some(&reference.name) == self.default_ref.as_ref() {
+                on_default_branch = true;
+            }
+            if !branches.contains(&reference.branch) {
+                branches.push(reference.branch.clone());
+            }
+        }
+        branches.sort();
+        (branches, on_default_branch)
+    }
+}
+
+/// A set of ref indexes, stored as a bitset.
+#[derive(Debug, Clone, Default)]
+struct RefSet(Vec<u64>);
+
+impl RefSet {
+    fn insert(&mut self, index: usize) {
+        let word = index / 64;
+        if self.0.len() <= word {
+            self.0.resize(word + 1, 0);
+        }
+        self.0[word] |= 1 << (index % 64);
+    }
+
+    fn union(&mut self, other: &RefSet) {
+        if self.0.len() < other.0.len() {
+            self.0.resize(other.0.len(), 0);
+        }
+        for (word, other_word) in self.0.iter_mut().zip(&other.0) {
+            *word |= other_word;
+        }
+    }
+
+    fn iter<'a>(&'a self) -> impl Iterator<Item = usize> + 'a {
+        self.0.iter().enumerate().flat_map(|(word_index, word)| {
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| word_index * 64 + bit)
+        })
+    }
+}
+
+/// Determines the ref of the default branch. Uses `RepoInfo::default_branch`
+/// if set, and otherwise where `origin/HEAD` points, `origin/main`,
+/// `origin/master` or the local `HEAD`, whichever exists first.
+pub fn default_branch(repo: &Repository, repo_info: &RepoInfo) -> Option<String> {
+    let candidates = match repo_info.default_branch {
+        Some(ref branch) if branch.starts_with("refs/") => vec![branch.clone()],
+        Some(ref branch) => vec![
+            format!("refs/remotes/origin/{}", branch),
+            format!("refs/heads/{}", branch),
+        ],
+        None => vec![
+            "refs/remotes/origin/HEAD".to_owned(),
+            "refs/remotes/origin/main".to_owned(),
+            "refs/remotes/origin/master".to_owned(),
+            "HEAD".to_owned(),
+        ],
+    };
+    candidates
+        .iter()
+        .filter_map(|name| repo.find_reference(name).ok())
+        .filter_map(|reference| reference.resolve().ok())
+        .filter_map(|reference| reference.name().map(|name| name.to_owned()))
+        .next()
+}
+
+/// Strips the `refs/heads/` or `refs/remotes/<remote>/` prefix from a ref.
+fn branch_name(name: &str) -> String {
+    if name.starts_with("refs/heads/") {
+        name["refs/heads/".len()..].to_owned()
+    } else if name.starts_with("refs/remotes/") {
+        let name = &name["refs/remotes/".len()..];
+        match name.find('/') {
+            Some(index) => name[index + 1..].to_owned(),
+            None => name.to_owned(),
+        }
+    } else {
+        name.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::build_test_logger;
+
+    #[test]
+    fn test_resolve_patterns() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/simple")?;
+        let repo_info = RepoInfo {
+            refs: vec![
+                "refs/remotes/origin/*".to_owned(),
+                "refs/heads/master".to_owned(),
+            ],
+            ..RepoInfo::default()
+        };
+        let branches = Branches::resolve(&repo, &repo_info, &build_test_logger())?;
+        let names: Vec<&str> = branches.refs().iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "refs/remotes/origin/feature",
+                "refs/remotes/origin/master",
+                "refs/heads/master"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_compute_reachability() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/simple")?;
+        let repo_info = RepoInfo {
+            refs: vec!["refs/remotes/origin/*".to_owned()],
+            ..RepoInfo::default()
+        };
+        let mut branches = Branches::resolve(&repo, &repo_info, &build_test_logger())?;
+        branches.compute_reachability(&repo, &[])?;
+        let branches_of = |sha: &str| branches.branches_of(Oid::from_str(sha).unwrap());
+        assert_eq!(
+            branches_of("86d242301830075e93ff039a4d1e88673a4a3020"),
+            (vec!["feature".to_owned(), "master".to_owned()], true)
+        );
+        assert_eq!(
+            branches_of("1b70351ae0018e6bf7fd4c9e3262a93b475003c4"),
+            (vec!["feature".to_owned(), "master".to_owned()], true)
+        );
+        assert_eq!(
+            branches_of("e3f67bc3313bc6a913da966afa0bb8a1bf043175"),
+            (vec!["master".to_owned()], true)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_default_branch() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/simple")?;
+        assert_eq!(
+            default_branch(&repo, &RepoInfo::default()),
+            Some("refs/remotes/origin/master".to_owned())
+        );
+        let repo_info = RepoInfo {
+            default_branch: Some("feature".to_owned()),
+            ..RepoInfo::default()
+        };
+        assert_eq!(
+            default_branch(&repo, &repo_info),
+            Some("refs/remotes/origin/feature".to_owned())
+        );
+        let branches = Branches::resolve(&repo, &RepoInfo::default(), &build_test_logger())?;
+        assert_eq!(branches.refs().len(), 1);
+        assert_eq!(branches.refs()[0].branch, "master");
+        Ok(())
+    }
+
+    #[test]
+    fn test_branch_name() {
+        assert_eq!(branch_name("refs/heads/master"), "master");
+        assert_eq!(
+            branch_name("refs/remotes/origin/release/1.0"),
+            "release/1.0"
+        );
+        assert_eq!(branch_name("HEAD"), "HEAD");
+    }
+}
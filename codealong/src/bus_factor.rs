@@ -0,0 +1,189 @@
+use chrono::prelude::*;
+use chrono::{DateTime, Duration};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+use crate::event::Event;
+use crate::ownership::Ownership;
+use crate::repo_info::PartialRepoInfo;
+
+/// Options for `RepoAnalyzer::analyze_bus_factor`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BusFactorOpts {
+    /// The fraction of lines the owners of an area need to own together.
+    pub threshold: f64,
+
+    /// How long owners need to have gone without authoring a commit to be
+    /// considered inactive.
+    pub inactive_after: Duration,
+
+    /// When inactivity is measured from.
+    pub as_of: DateTime<Utc>,
+}
+
+impl Default for BusFactorOpts {
+    fn default() -> Self {
+        BusFactorOpts {
+            threshold: 0.5,
+            inactive_after: Duration::days(90),
+            as_of: Utc::now(),
+        }
+    }
+}
+
+/// How concentrated the knowledge of a directory is: the smallest set of
+/// contributors owning more than the threshold of its lines.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BusFactor {
+    pub id: String,
+
+    /// The path of the directory. Empty for the repository root.
+    pub path: String,
+
+    pub commit_id: String,
+    pub snapshot_at: DateTime<Utc>,
+
+    pub lines: usize,
+    pub threshold: f64,
+
+    /// The number of owners, i.e. how many contributors would need to leave
+    /// for most of the knowledge of the directory to be lost.
+    pub bus_factor: usize,
+
+    /// The owners, most lines first.
+    pub owners: Vec<Owner>,
+
+    pub inactive_owners: usize,
+
+    /// Whether all owners are inactive.
+    pub at_risk: bool,
+
+    pub repo: Option<PartialRepoInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Owner {
+    pub id: String,
+    pub lines: usize,
+    pub last_active_at: Option<DateTime<Utc>>,
+    pub inactive: bool,
+}
+
+impl BusFactor {
+    /// Computes the bus factor of `ownership`, where `last_active_at` holds
+    /// when each contributor last authored a commit.
+    pub fn new(
+        ownership: &Ownership,
+        last_active_at: &HashMap<String, DateTime<Utc>>,
+        opts: &BusFactorOpts,
+    ) -> BusFactor {
+        let mut owners = Vec::new();
+        let mut owned = 0;
+        for contributor in &ownership.contributors {
+            if owned as f64 > opts.threshold * ownership.lines as f64 {
+                break;
+            }
+            owned += contributor.lines;
+            let last_active_at = last_active_at.get(&contributor.id).cloned();
+            owners.push(Owner {
+                id: contributor.id.clone(),
+                lines: contributor.lines,
+                last_active_at,
+                inactive: last_active_at
+                    .map(|at| opts.as_of - at > opts.inactive_after)
+                    .unwrap_or(true),
+            });
+        }
+        let inactive_owners = owners.iter().filter(|owner| owner.inactive).count();
+        BusFactor {
+            id: format!("{}:bus_factor", ownership.id),
+            path: ownership.path.clone(),
+            commit_id: ownership.commit_id.clone(),
+            snapshot_at: ownership.snapshot_at,
+            lines: ownership.lines,
+            threshold: opts.threshold,
+            bus_factor: owners.len(),
+            at_risk: !owners.is_empty() && inactive_owners == owners.len(),
+            inactive_owners,
+            owners,
+            repo: ownership.repo.clone(),
+        }
+    }
+}
+
+impl Event for BusFactor {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.snapshot_at
+    }
+
+    fn event_type(&self) -> &str {
+        "bus_factor"
+    }
+
+    fn id(&self) -> Cow<str> {
+        Cow::Borrowed(&self.id)
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        HashSet::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ownership::OwnedLines;
+
+    fn build_ownership(contributors: &[(&str, usize)]) -> Ownership {
+        Ownership {
+            id: "repo:abc:src".to_owned(),
+            path: "src".to_owned(),
+            directory: true,
+            commit_id: "abc".to_owned(),
+            snapshot_at: Utc.ymd(2019, 6, 1).and_hms(0, 0, 0),
+            lines: contributors.iter().map(|(_, lines)| lines).sum(),
+            contributors: contributors
+                .iter()
+                .map(|(id, lines)| OwnedLines {
+                    id: id.to_string(),
+                    lines: *lines,
+                })
+                .collect(),
+            teams: Vec::new(),
+            repo: None,
+        }
+    }
+
+    #[test]
+    fn test_bus_factor() {
+        let ownership = build_ownership(&[("alice", 40), ("bob", 30), ("carol", 30)]);
+        let mut last_active_at = HashMap::new();
+        last_active_at.insert("alice".to_owned(), Utc.ymd(2019, 1, 1).and_hms(0, 0, 0));
+        last_active_at.insert("bob".to_owned(), Utc.ymd(2019, 5, 1).and_hms(0, 0, 0));
+        let opts = BusFactorOpts {
+            as_of: Utc.ymd(2019, 6, 1).and_hms(0, 0, 0),
+            ..BusFactorOpts::default()
+        };
+        let bus_factor = BusFactor::new(&ownership, &last_active_at, &opts);
+        assert_eq!(bus_factor.bus_factor, 2);
+        let owners: Vec<(&str, bool)> = bus_factor
+            .owners
+            .iter()
+            .map(|owner| (owner.id.as_str(), owner.inactive))
+            .collect();
+        assert_eq!(owners, vec![("alice", true), ("bob", false)]);
+        assert_eq!(bus_factor.inactive_owners, 1);
+        assert!(!bus_factor.at_risk);
+
+        // exactly half is not more than half
+        let ownership = build_ownership(&[("alice", 50), ("carol", 50)]);
+        let bus_factor = BusFactor::new(&ownership, &last_active_at, &opts);
+        assert_eq!(bus_factor.bus_factor, 2);
+        assert!(bus_factor.at_risk);
+
+        let ownership = build_ownership(&[("alice", 60), ("bob", 40)]);
+        let bus_factor = BusFactor::new(&ownership, &last_active_at, &opts);
+        assert_eq!(bus_factor.bus_factor, 1);
+        assert!(bus_factor.at_risk);
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use git2::{ObjectType, Oid, Repository, Tree};
+
+use crate::contributor::Contributor;
+use crate::error::*;
+use crate::path_matcher::PathMatcher;
+
+/// The owners of each path according to a `CODEOWNERS` file.
+///
+/// Patterns follow the GitHub semantics: they are matched like gitignore
+/// entries, a pattern matching a directory owns everything beneath it and the
+/// last matching pattern takes precedence. Owners are `@user` and
+/// `@org/team` handles or email addresses.
+pub struct CodeOwners {
+    matcher: PathMatcher,
+    owners: Vec<Vec<String>>,
+}
+
+/// The owners of a set of changed files, and how many of them were changed by
+/// someone outside of their owning teams.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeOwners {
+    pub owners: Vec<String>,
+    pub cross_team_changes: usize,
+}
+
+impl CodeOwners {
+    /// Where GitHub looks for the file, in order.
+    pub const PATHS: [&'static str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+    pub fn parse(content: &str) -> CodeOwners {
+        let mut patterns = Vec::new();
+        let mut owners = Vec::new();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let pattern = parts.next().unwrap();
+            patterns.push(format!("regex:{}", pattern_to_regex(pattern)));
+            owners.push(
+                parts
+                    .take_while(|owner| !owner.starts_with('#'))
+                    .map(|owner| owner.to_owned())
+                    .collect(),
+            );
+        }
+        CodeOwners {
+            matcher: PathMatcher::new(patterns.iter().map(|p| (p.as_str(), false))),
+            owners,
+        }
+    }
+
+    /// Reads the `CODEOWNERS` file committed in `tree`, if any.
+    pub fn from_tree(repo: &Repository, tree: &Tree) -> Result<Option<CodeOwners>> {
+        match Self::find_blob(tree) {
+            Some(oid) => Ok(Some(Self::from_blob(repo, oid)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Locates the `CODEOWNERS` file within `tree` and returns its blob id.
+    pub fn find_blob(tree: &Tree) -> Option<Oid> {
+        Self::PATHS
+            .iter()
+            .filter_map(|path| tree.get_path(Path::new(path)).ok())
+            .find(|entry| entry.kind() == Some(ObjectType::Blob))
+            .map(|entry| entry.id())
+    }
+
+    pub fn from_blob(repo: &Repository, oid: Oid) -> Result<CodeOwners> {
+        let blob = repo.find_blob(oid)?;
+        Ok(Self::parse(&String::from_utf8_lossy(blob.content())))
+    }
+
+    /// The owners of `path`, empty if it has none.
+    pub fn owners_for_path(&self, path: &[u8]) -> &[String] {
+        match self.matcher.matches(path).iter().rposition(|m| *m) {
+            Some(index) => &self.owners[index],
+            None => &[],
+        }
+    }
+
+    /// The owners of `paths` along with the number of owned paths none of
+    /// whose owners is `author` or a team of theirs.
+    pub fn change_owners<I, P>(&self, paths: I, author: &Contributor) -> ChangeOwners
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<[u8]>,
+    {
+        let mut owners = BTreeSet::new();
+        let mut cross_team_changes = 0;
+        for path in paths {
+            let path_owners = self.owners_for_path(path.as_ref());
+            if path_owners.is_empty() {
+                continue;
+            }
+            if !path_owners.iter().any(|owner| is_owned_by(author, owner)) {
+                cross_team_changes += 1;
+            }
+            owners.extend(path_owners.iter().cloned());
+        }
+        ChangeOwners {
+            owners: owners.into_iter().collect(),
+            cross_team_changes,
+        }
+    }
+}
+
+/// Whether `owner` refers to `contributor` or one of their teams. Teams are
+/// matched by their slug, with or without the organization.
+fn is_owned_by(contributor: &Contributor, owner: &str) -> bool {
+    if owner.starts_with('@') {
+        let handle = &owner[1..];
+        match handle.find('/') {
+            Some(index) => contributor.teams.iter().any(|team| {
+                team.eq_ignore_ascii_case(handle) || team.eq_ignore_ascii_case(&handle[index + 1..])
+            }),
+            None => contributor
+                .github_logins
+                .iter()
+                .any(|login| login.eq_ignore_ascii_case(handle)),
+        }
+    } else {
+        contributor.identities.iter().any(|identity| {
+            identity
+                .email
+                .as_ref()
+                .map(|email| email.eq_ignore_ascii_case(owner))
+                .unwrap_or(false)
+        })
+    }
+}
+
+/// Translates a gitignore style pattern into a regular expression matching
+/// the paths it owns.
+fn pattern_to_regex(pattern: &str) -> String {
+    let directory = pattern.ends_with('/');
+    let pattern = pattern.trim_end_matches('/');
+    // a trailing single `*` only matches the entries of a directory, not
+    // their descendants
+    let entries = pattern.ends_with('*') && !pattern.ends_with("**");
+    // patterns containing a slash other than a trailing one are relative to
+    // the root, others match at any depth
+    let anchored = pattern.contains('/');
+    let pattern = pattern.trim_start_matches('/');
+    let mut regex = String::from(if anchored { "^" } else { "^(?:.*/)?" });
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push_str(if directory {
+        "/.*$"
+    } else if entries {
+        "$"
+    } else {
+        "(?:/.*)?$"
+    });
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Identity;
+
+    const CODEOWNERS: &str = "
+# default owners
+*       @acme/core
+
+*.md    docs@example.com
+/src/api/ @acme/api @carol
+docs/   # unowned
+/config/* @acme/ops
+";
+
+    #[test]
+    fn test_owners_for_path() {
+        let code_owners = CodeOwners::parse(CODEOWNERS);
+        let owners = |path: &str| code_owners.owners_for_path(path.as_bytes()).to_vec();
+        assert_eq!(owners("Cargo.toml"), vec!["@acme/core"]);
+        assert_eq!(owners("src/api/README.md"), vec!["@acme/api", "@carol"]);
+        assert_eq!(owners("src/lib/README.md"), vec!["docs@example.com"]);
+        assert_eq!(owners("lib/src/api/mod.rs"), vec!["@acme/core"]);
+        assert!(owners("docs/guide.txt").is_empty());
+        assert!(owners("lib/docs/guide.txt").is_empty());
+        assert_eq!(owners("config/app.yml"), vec!["@acme/ops"]);
+        assert_eq!(owners("config/env/prod.yml"), vec!["@acme/core"]);
+    }
+
+    #[test]
+    fn test_change_owners() {
+        let code_owners = CodeOwners::parse(CODEOWNERS);
+        let author = Contributor {
+            teams: vec!["api".to_owned()],
+            identities: vec![Identity::parse("Alice <alice@example.com>")],
+            ..Contributor::default()
+        };
+        let paths = ["src/api/mod.rs", "src/lib.rs", "docs/guide.txt"];
+        assert_eq!(
+            code_owners.change_owners(&paths, &author),
+            ChangeOwners {
+                owners: vec![
+                    "@acme/api".to_owned(),
+                    "@acme/core".to_owned(),
+                    "@carol".to_owned()
+                ],
+                cross_team_changes: 1,
+            }
+        );
+    }
+}
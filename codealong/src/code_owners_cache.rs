@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use git2::{Oid, Repository, Tree};
+
+use crate::code_owners::CodeOwners;
+use crate::error::*;
+
+/// Provides the `CODEOWNERS` file committed in a given tree.
+///
+/// Files are cached by their blob ids, so each distinct version is only
+/// parsed once per analysis rather than once per commit. The cache is shared
+/// by the workers of a parallel analysis.
+pub struct CodeOwnersCache {
+    code_owners: Mutex<HashMap<Oid, Arc<CodeOwners>>>,
+}
+
+impl CodeOwnersCache {
+    pub fn new() -> CodeOwnersCache {
+        CodeOwnersCache {
+            code_owners: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The `CODEOWNERS` file committed in `tree`, if any.
+    pub fn code_owners_for_tree(
+        &self,
+        repo: &Repository,
+        tree: &Tree,
+    ) -> Result<Option<Arc<CodeOwners>>> {
+        let oid = match CodeOwners::find_blob(tree) {
+            Some(oid) => oid,
+            None => return Ok(None),
+        };
+        if let Some(code_owners) = self.code_owners.lock().unwrap().get(&oid) {
+            return Ok(Some(code_owners.clone()));
+        }
+        let code_owners = Arc::new(CodeOwners::from_blob(repo, oid)?);
+        self.code_owners
+            .lock()
+            .unwrap()
+            .insert(oid, code_owners.clone());
+        Ok(Some(code_owners))
+    }
+}
+
+impl Default for CodeOwnersCache {
+    fn default() -> CodeOwnersCache {
+        CodeOwnersCache::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::contributor::Contributor;
+
+    #[test]
+    fn test_code_owners_for_tree() -> Result<()> {
+        let dir = tempdir::TempDir::new("codealong")?;
+        let repo = Repository::init_bare(dir.path())?;
+        let build_tree = |files: &[(&str, &[u8])]| -> Result<Oid> {
+            let mut builder = repo.treebuilder(None)?;
+            for (path, content) in files {
+                builder.insert(path, repo.blob(content)?, 0o100644)?;
+            }
+            Ok(builder.write()?)
+        };
+        let owners: &[u8] = b"*.rb @acme/web\n";
+        let trees = [
+            build_tree(&[("CODEOWNERS", owners), ("a.rb", b"a")])?,
+            build_tree(&[("CODEOWNERS", owners), ("a.rb", b"b")])?,
+            build_tree(&[("a.rb", b"c")])?,
+        ];
+        let cache = CodeOwnersCache::new();
+        let first = cache
+            .code_owners_for_tree(&repo, &repo.find_tree(trees[0])?)?
+            .unwrap();
+        let second = cache
+            .code_owners_for_tree(&repo, &repo.find_tree(trees[1])?)?
+            .unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        let change_owners = first.change_owners(vec!["a.rb"], &Contributor::default());
+        assert_eq!(change_owners.owners, vec!["@acme/web"]);
+        assert!(cache
+            .code_owners_for_tree(&repo, &repo.find_tree(trees[2])?)?
+            .is_none());
+        Ok(())
+    }
+}
@@ -1,18 +1,39 @@
-use git2::{Commit, Repository};
+use chrono::{DateTime, Utc};
+use git2::{Commit, Oid, Repository};
 use slog::Logger;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::Arc;
 
+use crate::analysis_cache::{classification_hash, AnalysisCache, CachedCommit, CachedFile};
+use crate::analyzed_diff::AnalyzedDiff;
 use crate::analyzed_commit::AnalyzedCommit;
+use crate::code_owners_cache::CodeOwnersCache;
 use crate::diff_analyzer::DiffAnalyzer;
 use crate::error::*;
+use crate::project::Project;
 use crate::repo_info::RepoInfo;
+use crate::submodule_update::{submodule_repo_name, submodule_urls, SubmoduleUpdate};
+use crate::szz::{blame_bug_fix, bug_introducing_commits, BlamedLines};
 use crate::working_config::WorkingConfig;
 use crate::identity::Identity;
 
 pub struct CommitAnalyzer<'a> {
     repo: &'a Repository,
     commit: Commit<'a>,
-    config: &'a WorkingConfig,
+    config: Arc<WorkingConfig>,
     repo_info: &'a RepoInfo,
+    cache: Option<Arc<AnalysisCache>>,
+    code_owners_cache: Arc<CodeOwnersCache>,
+    blame_bug_fixes: bool,
+    branches: Vec<String>,
+    on_default_branch: bool,
+    released_at: Option<DateTime<Utc>>,
+    pull_request_number: Option<u64>,
+    reverts: Option<Oid>,
+    reverted_by: Option<Oid>,
+    submodule_repos: Arc<BTreeMap<String, PathBuf>>,
+    projects: Arc<Vec<Project>>,
     logger: Logger,
 }
 
@@ -20,7 +41,7 @@ impl<'a> CommitAnalyzer<'a> {
     pub fn new(
         repo: &'a Repository,
         commit: Commit<'a>,
-        config: &'a WorkingConfig,
+        config: Arc<WorkingConfig>,
         repo_info: &'a RepoInfo,
         parent_logger: &Logger,
     ) -> CommitAnalyzer<'a> {
@@ -30,25 +51,316 @@ impl<'a> CommitAnalyzer<'a> {
             commit,
             config,
             repo_info,
+            cache: None,
+            code_owners_cache: Arc::new(CodeOwnersCache::new()),
+            blame_bug_fixes: false,
+            branches: Vec::new(),
+            on_default_branch: false,
+            released_at: None,
+            pull_request_number: None,
+            reverts: None,
+            reverted_by: None,
+            submodule_repos: Arc::new(BTreeMap::new()),
+            projects: Arc::new(Vec::new()),
             logger,
         }
     }
 
+    /// Sets the branches the commit is reachable from.
+    pub fn set_branches(&mut self, branches: Vec<String>, on_default_branch: bool) {
+        self.branches = branches;
+        self.on_default_branch = on_default_branch;
+    }
+
+    pub(crate) fn branches(&self) -> (&[String], bool) {
+        (&self.branches, self.on_default_branch)
+    }
+
+    /// Sets when the first release containing the commit was released.
+    pub fn set_released_at(&mut self, released_at: Option<DateTime<Utc>>) {
+        self.released_at = released_at;
+    }
+
+    pub(crate) fn released_at(&self) -> Option<DateTime<Utc>> {
+        self.released_at
+    }
+
+    /// Sets the pull request the commit was merged through.
+    pub fn set_pull_request_number(&mut self, pull_request_number: Option<u64>) {
+        self.pull_request_number = pull_request_number;
+    }
+
+    pub(crate) fn pull_request_number(&self) -> Option<u64> {
+        self.pull_request_number
+    }
+
+    /// Sets the commit this commit reverts and the one reverting it.
+    pub fn set_reverts(&mut self, reverts: Option<Oid>, reverted_by: Option<Oid>) {
+        self.reverts = reverts;
+        self.reverted_by = reverted_by;
+    }
+
+    pub(crate) fn reverts(&self) -> (Option<Oid>, Option<Oid>) {
+        (self.reverts, self.reverted_by)
+    }
+
+    /// Sets the local clones of the repositories submodules may point to,
+    /// keyed by the name `submodule_repo_name` derives from their url.
+    pub fn set_submodule_repos(&mut self, submodule_repos: Arc<BTreeMap<String, PathBuf>>) {
+        self.submodule_repos = submodule_repos;
+    }
+
+    pub(crate) fn submodule_repos(&self) -> &Arc<BTreeMap<String, PathBuf>> {
+        &self.submodule_repos
+    }
+
+    /// Sets the projects `analyze_projects` splits the commit by.
+    pub fn set_projects(&mut self, projects: Arc<Vec<Project>>) {
+        self.projects = projects;
+    }
+
+    pub(crate) fn projects(&self) -> &Arc<Vec<Project>> {
+        &self.projects
+    }
+
+    /// Reuses the line classification stored in `cache` if possible and
+    /// stores it there otherwise.
+    pub fn set_cache(&mut self, cache: Arc<AnalysisCache>) {
+        self.cache = Some(cache);
+    }
+
+    pub(crate) fn cache(&self) -> Option<&Arc<AnalysisCache>> {
+        self.cache.as_ref()
+    }
+
+    /// Reuses the `CODEOWNERS` files parsed by other commits sharing
+    /// `code_owners_cache`.
+    pub fn set_code_owners_cache(&mut self, code_owners_cache: Arc<CodeOwnersCache>) {
+        self.code_owners_cache = code_owners_cache;
+    }
+
+    pub(crate) fn code_owners_cache(&self) -> &Arc<CodeOwnersCache> {
+        &self.code_owners_cache
+    }
+
+    /// Blames the lines changed by bug fixes to find the commits which
+    /// introduced the bugs. Off by default, as it runs blame on every file a
+    /// bug fix changes.
+    pub fn set_blame_bug_fixes(&mut self, blame_bug_fixes: bool) {
+        self.blame_bug_fixes = blame_bug_fixes;
+    }
+
+    pub(crate) fn blame_bug_fixes(&self) -> bool {
+        self.blame_bug_fixes
+    }
+
     pub fn analyze(&self) -> Result<AnalyzedCommit> {
         let mut result = AnalyzedCommit::new(&self.commit);
         debug!(self.logger, "Analyzing commit"; "commit_time" => &result.authored_at.to_rfc2822(), "commit_author" => &result.author.to_string(), "commit_summary" => &result.summary);
+        let (diff, mut cached_commit) = self.analyze_diff(&result.author)?;
+        self.blame_bug_fix(&mut cached_commit);
+        result.merge_diff(&diff);
+        let blamed_lines = cached_commit.blamed_lines.as_ref().map(Vec::as_slice);
+        self.annotate(&mut result, None, &cached_commit.files, blamed_lines)?;
+        debug!(self.logger, "Done analyzing");
+        Ok(result)
+    }
+
+    /// Analyzes only the diff and author of the commit, which is all that
+    /// aggregates such as releases need. The line classification is reused
+    /// from the cache if possible and, unlike `analyze`, bug introducing
+    /// commits, code owners and submodules are not looked up.
+    pub fn analyze_stats(&self) -> Result<AnalyzedCommit> {
+        let mut result = AnalyzedCommit::new(&self.commit);
+        let (diff, _) = self.analyze_diff(&result.author)?;
+        result.merge_diff(&diff);
+        result.normalized_author = Some(self.config.contributor_for_identity(&result.author));
+        Ok(result)
+    }
+
+    /// Analyzes the commit once for each of the `projects` it changes,
+    /// restricted to the files of the project, and once more for the files
+    /// outside of any project. Without projects, this is the same as
+    /// `analyze`.
+    pub fn analyze_projects(&self) -> Result<Vec<AnalyzedCommit>> {
+        if self.projects.is_empty() {
+            return Ok(vec![self.analyze()?]);
+        }
+        let author = Identity::from(self.commit.author());
+        debug!(self.logger, "Analyzing commit by project");
+        let (_, mut cached_commit) = self.analyze_diff(&author)?;
+        self.blame_bug_fix(&mut cached_commit);
+        let blamed_lines = cached_commit.blamed_lines.take();
+        let mut files = cached_commit.files;
+        let mut results = Vec::new();
+        let parts = self.projects.iter().map(Some).chain(std::iter::once(None));
+        for project in parts {
+            let (part_files, rest): (Vec<CachedFile>, Vec<CachedFile>) =
+                files.into_iter().partition(|file| self.in_part(project, &file.path));
+            files = rest;
+            // commits only changing the files of projects have no part of
+            // their own
+            if part_files.is_empty() && (project.is_some() || !results.is_empty()) {
+                continue;
+            }
+            let config = project.map(|p| p.config()).unwrap_or(&self.config);
+            let mut result = AnalyzedCommit::new(&self.commit);
+            let cached_commit = CachedCommit {
+                classification_hash: classification_hash(config),
+                files: part_files,
+                blamed_lines: None,
+            };
+            let diff = match cached_commit.rebuild(config, &author) {
+                Some(diff) => diff,
+                // files ignored by the repo are not by the project, so they
+                // have to be classified under the project's config
+                None => {
+                    let (_, files) = self.classify(config)?;
+                    CachedCommit {
+                        classification_hash: classification_hash(config),
+                        files: files
+                            .into_iter()
+                            .filter(|file| self.in_part(project, &file.path))
+                            .collect(),
+                        blamed_lines: None,
+                    }
+                    .rebuild(config, &author)
+                    .unwrap_or_else(AnalyzedDiff::empty)
+                }
+            };
+            result.merge_diff(&diff);
+            self.annotate(
+                &mut result,
+                project,
+                &cached_commit.files,
+                blamed_lines.as_ref().map(Vec::as_slice),
+            )?;
+            results.push(result);
+        }
+        debug!(self.logger, "Done analyzing"; "parts" => results.len());
+        Ok(results)
+    }
+
+    /// The projects of the parts `analyze_projects` splits the commit into,
+    /// `None` standing for the files outside of any project.
+    pub fn part_projects(&self) -> Result<Vec<Option<String>>> {
+        if self.projects.is_empty() {
+            return Ok(vec![None]);
+        }
+        let paths = self.changed_paths()?;
+        let mut parts: Vec<Option<String>> = self
+            .projects
+            .iter()
+            .filter(|project| paths.iter().any(|path| project.contains(path)))
+            .map(|project| Some(project.name().to_owned()))
+            .collect();
+        if parts.is_empty() || paths.iter().any(|path| self.in_part(None, path)) {
+            parts.push(None);
+        }
+        Ok(parts)
+    }
+
+    /// Whether `path` belongs to `project`, or to no project if not given.
+    fn in_part(&self, project: Option<&Project>, path: &str) -> bool {
+        match project {
+            Some(project) => project.contains(path),
+            None => !self.projects.iter().any(|project| project.contains(path)),
+        }
+    }
+
+    /// The analyzed diff of the commit along with the classification of each
+    /// changed file, rebuilt from the cache if possible.
+    fn analyze_diff(&self, author: &Identity) -> Result<(AnalyzedDiff, CachedCommit)> {
+        let cached_commit = self.cache.as_ref().and_then(|cache| cache.get(self.commit.id()));
+        if let Some(cached_commit) = cached_commit {
+            if let Some(diff) = cached_commit.rebuild(&self.config, author) {
+                debug!(self.logger, "Rebuilt from cache");
+                return Ok((diff, cached_commit));
+            }
+        }
+        let (diff, files) = self.classify(&self.config)?;
+        let cached_commit = CachedCommit {
+            classification_hash: classification_hash(&self.config),
+            files,
+            blamed_lines: None,
+        };
+        self.store(&cached_commit);
+        Ok((diff, cached_commit))
+    }
+
+    /// Blames the lines changed by the commit if it is a bug fix and bug
+    /// fixes are blamed, unless `cached_commit` already holds the result.
+    fn blame_bug_fix(&self, cached_commit: &mut CachedCommit) {
+        if !self.blame_bug_fixes || cached_commit.blamed_lines.is_some() {
+            return;
+        }
+        let message = self.commit.message().unwrap_or("");
+        let is_bug_fix = self.config.is_bug_fix(message)
+            || self
+                .projects
+                .iter()
+                .any(|project| project.config().is_bug_fix(message));
+        if !is_bug_fix {
+            return;
+        }
+        match blame_bug_fix(self.repo, &self.commit, &self.config) {
+            Ok(blamed_lines) => {
+                cached_commit.blamed_lines = Some(blamed_lines);
+                self.store(cached_commit);
+            }
+            Err(e) => {
+                let error = e.to_string();
+                warn!(self.logger, "Could not blame bug fix"; "error" => error);
+            }
+        }
+    }
+
+    fn store(&self, cached_commit: &CachedCommit) {
+        if let Some(ref cache) = self.cache {
+            if let Err(e) = cache.put(self.commit.id(), cached_commit) {
+                let error = e.to_string();
+                warn!(self.logger, "Could not cache analysis"; "error" => error);
+            }
+        }
+    }
+
+    /// Classifies the lines changed relative to each parent under `config`.
+    fn classify(&self, config: &WorkingConfig) -> Result<(AnalyzedDiff, Vec<CachedFile>)> {
+        let mut result = AnalyzedDiff::empty();
+        let mut files = Vec::new();
         // TODO: deal with merge commits
         let mut has_parents = false;
         for parent in self.commit.parents() {
-            let diff_analyzer =
-                DiffAnalyzer::new(self.repo, &self.commit, Some(&parent), &self.config);
-            result.merge_diff(&diff_analyzer.analyze()?);
+            let diff_analyzer = DiffAnalyzer::new(self.repo, &self.commit, Some(&parent), config);
+            let (diff, cached_files) = diff_analyzer.analyze_with_cached_files()?;
+            result += diff;
+            files.extend(cached_files);
             has_parents = true;
         }
         // handle initial commit
         if !has_parents {
-            let diff_analyzer = DiffAnalyzer::new(self.repo, &self.commit, None, &self.config);
-            result.merge_diff(&diff_analyzer.analyze()?);
+            let diff_analyzer = DiffAnalyzer::new(self.repo, &self.commit, None, config);
+            let (diff, cached_files) = diff_analyzer.analyze_with_cached_files()?;
+            result += diff;
+            files.extend(cached_files);
+        }
+        Ok((result, files))
+    }
+
+    /// Fills in everything but the diff of the commit, or of its part
+    /// belonging to `project` if given, whose changed `files` are used to
+    /// look up the code owners. The bug introducing commits are taken from
+    /// `blamed_lines`, if the commit was blamed as a bug fix.
+    fn annotate(
+        &self,
+        result: &mut AnalyzedCommit,
+        project: Option<&Project>,
+        files: &[CachedFile],
+        blamed_lines: Option<&[BlamedLines]>,
+    ) -> Result<()> {
+        let config = project.map(|p| p.config()).unwrap_or(&self.config);
+        if !result.diff.submodules.is_empty() {
+            self.resolve_submodules(&mut result.diff.submodules)?;
         }
         if let Some(ref github_name) = self.repo_info.github_name {
             result.github_url = Some(format!(
@@ -56,21 +368,112 @@ impl<'a> CommitAnalyzer<'a> {
                 github_name, result.id
             ));
         }
-        result.repo = Some(self.repo_info.partial());
-        result.normalized_author = Some(self.config.contributor_for_identity(&result.author));
-        result.normalized_committer = Some(self.config.contributor_for_identity(&result.committer));
-        debug!(self.logger, "Done analyzing");
-        return Ok(result);
+        result.repo = Some(match project {
+            Some(project) => self.repo_info.project_partial(project.name()),
+            None => self.repo_info.partial(),
+        });
+        result.project = project.map(|p| p.name().to_owned());
+        result.branches = self.branches.clone();
+        result.on_default_branch = self.on_default_branch;
+        if let Some(released_at) = self.released_at {
+            result.set_released_at(released_at);
+        }
+        result.set_pull_request_number(self.pull_request_number);
+        result.reverts = self.reverts.map(|oid| oid.to_string());
+        result.reverted_by = self.reverted_by.map(|oid| oid.to_string());
+        if config.is_bug_fix(self.commit.message().unwrap_or("")) {
+            result.bug_fix = true;
+            if let Some(blamed_lines) = blamed_lines {
+                // files may have been ignored since they were blamed
+                let include = |path: &str| {
+                    let ignored = config
+                        .config_for_file(path)
+                        .map(|c| c.ignore())
+                        .unwrap_or(false);
+                    !ignored && self.in_part(project, path)
+                };
+                result.bug_introducing_commits = bug_introducing_commits(blamed_lines, &include);
+            }
+        }
+        let normalized_author = config.contributor_for_identity(&result.author);
+        let code_owners = self
+            .code_owners_cache
+            .code_owners_for_tree(self.repo, &self.commit.tree()?)?;
+        if let Some(code_owners) = code_owners {
+            // merge commits list the files changed relative to each parent
+            let paths: BTreeSet<&str> = files.iter().map(|file| file.path.as_str()).collect();
+            let change_owners = code_owners.change_owners(paths, &normalized_author);
+            result.set_change_owners(change_owners);
+        }
+        result.set_working_hours(config.working_hours_for_identity(&result.author));
+        result.is_author_known = config.is_known(&result.author);
+        result.normalized_author = Some(normalized_author);
+        result.normalized_committer = Some(config.contributor_for_identity(&result.committer));
+        Ok(())
+    }
+
+    /// Fills in the url of each updated submodule, along with the commits it
+    /// brought in if its repository is one of the `submodule_repos`.
+    fn resolve_submodules(&self, submodules: &mut [SubmoduleUpdate]) -> Result<()> {
+        let urls = submodule_urls(self.repo, &self.commit.tree()?)?;
+        for submodule in submodules {
+            submodule.url = urls.get(&submodule.path).cloned();
+            let path = submodule
+                .url
+                .as_ref()
+                .and_then(|url| submodule_repo_name(url))
+                .and_then(|name| self.submodule_repos.get(&name));
+            if let Some(path) = path {
+                let resolved = Repository::open(path)
+                    .map_err(Error::from)
+                    .and_then(|repo| submodule.resolve_commits(&repo));
+                if let Err(e) = resolved {
+                    let error = e.to_string();
+                    warn!(self.logger, "Could not resolve submodule commits"; "path" => &submodule.path, "error" => error);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The paths changed relative to any of the parents.
+    fn changed_paths(&self) -> Result<Vec<String>> {
+        let mut paths = Vec::new();
+        let parents: Vec<Commit> = self.commit.parents().collect();
+        if parents.is_empty() {
+            let diff_analyzer = DiffAnalyzer::new(self.repo, &self.commit, None, &self.config);
+            paths.extend(diff_analyzer.changed_paths()?);
+        }
+        for parent in &parents {
+            let diff_analyzer =
+                DiffAnalyzer::new(self.repo, &self.commit, Some(parent), &self.config);
+            paths.extend(diff_analyzer.changed_paths()?);
+        }
+        paths.sort();
+        paths.dedup();
+        Ok(paths)
+    }
+
+    pub fn commit(&self) -> &Commit<'a> {
+        &self.commit
+    }
+
+    pub(crate) fn config(&self) -> &Arc<WorkingConfig> {
+        &self.config
     }
 
     pub fn is_author_known(&self) -> bool {
-        self.config.is_known(&Identity::from(self.commit.author())
+        self.config.is_known(&Identity::from(self.commit.author()))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::binary_stats::BinaryStats;
+    use crate::config::{Config, ContributorConfig, GlobConfig, WorkingHours};
+    use crate::contributor::Contributor;
+    use crate::event::Event;
     use crate::repo_config::RepoConfig;
     use crate::test::build_test_logger;
     use crate::work_stats::WorkStats;
@@ -83,12 +486,55 @@ mod tests {
         let commit = repo
             .find_commit(Oid::from_str("86d242301830075e93ff039a4d1e88673a4a3020").unwrap())
             .unwrap();
-        let config = WorkingConfig::default();
+        let config = Arc::new(WorkingConfig::default());
         let repo_info = RepoInfo::default();
-        let analyzer =
-            CommitAnalyzer::new(&repo, commit, &config, &repo_info, &build_test_logger());
+        let analyzer = CommitAnalyzer::new(&repo, commit, config, &repo_info, &build_test_logger());
         let res = analyzer.analyze().unwrap();
         assert_eq!(res.diff.stats.new_work, 1);
+        assert!(!res.is_author_known);
+    }
+
+    #[test]
+    fn test_working_hours() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/simple")?;
+        let oid = Oid::from_str("86d242301830075e93ff039a4d1e88673a4a3020")?;
+        let repo_info = RepoInfo::default();
+        let analyze = |config: Config| {
+            let config = Arc::new(WorkingConfig::new(config));
+            let commit = repo.find_commit(oid)?;
+            CommitAnalyzer::new(&repo, commit, config, &repo_info, &build_test_logger()).analyze()
+        };
+
+        // authored on a Tuesday at 13:37 -07:00
+        let res = analyze(Config::default())?;
+        assert_eq!(res.author_utc_offset_minutes, -7 * 60);
+        assert_eq!(res.local_hour, 13);
+        assert_eq!(res.local_weekday, "Tue");
+        assert!(!res.is_weekend);
+        assert!(!res.is_off_hours);
+
+        let mut config = Config::default();
+        config.team_working_hours.insert(
+            "late".to_owned(),
+            WorkingHours {
+                start: 14,
+                end: 22,
+                ..WorkingHours::default()
+            },
+        );
+        config.contributors.push(ContributorConfig {
+            contributor: Contributor {
+                id: "gordon".to_owned(),
+                identities: vec![Identity::parse("Gordon Hempton <ghempton@gmail.com>")],
+                teams: vec!["late".to_owned()],
+                ..Contributor::default()
+            },
+            ..ContributorConfig::default()
+        });
+        let res = analyze(config)?;
+        assert!(!res.is_weekend);
+        assert!(res.is_off_hours);
+        Ok(())
     }
 
     #[test]
@@ -97,10 +543,9 @@ mod tests {
         let commit = repo
             .find_commit(Oid::from_str("bf51d175af7e82fbd43cb8598b45aa1830890ff0").unwrap())
             .unwrap();
-        let config = WorkingConfig::default();
+        let config = Arc::new(WorkingConfig::default());
         let repo_info = RepoInfo::default();
-        let analyzer =
-            CommitAnalyzer::new(&repo, commit, &config, &repo_info, &build_test_logger());
+        let analyzer = CommitAnalyzer::new(&repo, commit, config, &repo_info, &build_test_logger());
         let res = analyzer.analyze().unwrap();
         assert_eq!(
             res.diff.stats,
@@ -124,12 +569,228 @@ mod tests {
         let RepoConfig { config, repo: repo_info } = RepoConfig::from_path(Path::new(
             "./fixtures/configs/simple.yml",
         ))?;
-        let config = WorkingConfig::new(config);
+        let config = Arc::new(WorkingConfig::new(config));
         let analyzer =
-            CommitAnalyzer::new(&repo, commit, &config, &repo_info, &build_test_logger());
+            CommitAnalyzer::new(&repo, commit, config, &repo_info, &build_test_logger());
         let res = analyzer.analyze().unwrap();
         assert_eq!(res.github_url, Some("https://github.com/ghempton/codealong/commit/86d242301830075e93ff039a4d1e88673a4a3020".to_string()));
         assert_eq!(res.diff.tag_stats.get("docs").unwrap().new_work, 1);
+        assert!(res.is_author_known);
+        Ok(())
+    }
+
+    #[test]
+    fn test_module_stats() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/coupling")?;
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push_head()?;
+        let initial_commit = revwalk.last().unwrap()?;
+        let mut config = Config::default();
+        config
+            .modules
+            .insert("documentation".to_owned(), vec!["docs".to_owned()]);
+        config.module_depth = Some(1);
+        let config = Arc::new(WorkingConfig::new(config));
+        let repo_info = RepoInfo::default();
+        let analyzer = CommitAnalyzer::new(
+            &repo,
+            repo.find_commit(initial_commit)?,
+            config,
+            &repo_info,
+            &build_test_logger(),
+        );
+        let res = analyzer.analyze()?;
+        assert_eq!(res.diff.stats.new_work, 4);
+        assert_eq!(res.diff.module_stats.len(), 2);
+        assert_eq!(res.diff.module_stats["src"].new_work, 2);
+        assert_eq!(res.diff.module_stats["documentation"].new_work, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_submodule_update() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/submodules")?;
+        let commit = repo.head()?.peel_to_commit()?;
+        let lib =
+            |spec: &str| -> Result<String> { Ok(repo.revparse_single(spec)?.id().to_string()) };
+        let config = Arc::new(WorkingConfig::default());
+        let repo_info = RepoInfo::default();
+        let mut analyzer =
+            CommitAnalyzer::new(&repo, commit, config, &repo_info, &build_test_logger());
+        let mut submodule_repos = BTreeMap::new();
+        submodule_repos.insert(
+            "example/lib".to_owned(),
+            PathBuf::from("./fixtures/repos/submodules"),
+        );
+        analyzer.set_submodule_repos(Arc::new(submodule_repos));
+        let res = analyzer.analyze()?;
+
+        // only the line added to the README counts, not the submodule commit
+        assert_eq!(res.diff.stats.new_work, 1);
+        assert_eq!(res.diff.stats.other, 0);
+        assert!(res.tags().contains("submodule"));
+        assert_eq!(
+            res.diff.submodules,
+            vec![SubmoduleUpdate {
+                path: "vendor/lib".to_owned(),
+                url: Some("https://github.com/example/lib.git".to_owned()),
+                old_commit: Some(lib("origin/lib~2")?),
+                new_commit: Some(lib("origin/lib")?),
+                commits: Some(vec![lib("origin/lib")?, lib("origin/lib~1")?]),
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_binary_files() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/binary")?;
+        let repo_info = RepoInfo::default();
+        let dir = tempdir::TempDir::new("codealong")?;
+        let cache = Arc::new(AnalysisCache::new(dir.path().to_owned()));
+        let analyze = || -> Result<AnalyzedCommit> {
+            let commit = repo.head()?.peel_to_commit()?;
+            let config = Arc::new(WorkingConfig::default());
+            let mut analyzer =
+                CommitAnalyzer::new(&repo, commit, config, &repo_info, &build_test_logger());
+            analyzer.set_cache(cache.clone());
+            analyzer.analyze()
+        };
+        let res = analyze()?;
+
+        // logo.png grows from 16 to 24 bytes, body.woff2 (8 bytes) is deleted
+        // and bundle.zip (10 bytes) is added
+        assert_eq!(
+            res.diff.binary,
+            BinaryStats {
+                added: 1,
+                modified: 1,
+                deleted: 1,
+                size_delta: 10,
+            }
+        );
+        assert_eq!(res.diff.stats.new_work, 1);
+        for binary_type in &["image", "font", "archive"] {
+            assert!(res.tags().contains(*binary_type));
+        }
+        assert_eq!(analyze()?, res);
+
+        // ignored binary files are not counted
+        let mut config = Config::default();
+        config.files.insert(
+            "assets/**/*".to_owned(),
+            GlobConfig {
+                ignore: true,
+                ..GlobConfig::default()
+            },
+        );
+        let commit = repo.head()?.peel_to_commit()?;
+        let config = Arc::new(WorkingConfig::new(config));
+        let analyzer = CommitAnalyzer::new(&repo, commit, config, &repo_info, &build_test_logger());
+        assert_eq!(analyzer.analyze()?.diff.binary, BinaryStats::empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/simple")?;
+        let oid = Oid::from_str("bf51d175af7e82fbd43cb8598b45aa1830890ff0")?;
+        let repo_info = RepoInfo::default();
+        let dir = tempdir::TempDir::new("codealong")?;
+        let cache = Arc::new(AnalysisCache::new(dir.path().to_owned()));
+        let analyze = |config: Config, cache: Option<Arc<AnalysisCache>>| {
+            let commit = repo.find_commit(oid).unwrap();
+            let config = Arc::new(WorkingConfig::new(config));
+            let mut analyzer =
+                CommitAnalyzer::new(&repo, commit, config, &repo_info, &build_test_logger());
+            if let Some(cache) = cache {
+                analyzer.set_cache(cache);
+            }
+            analyzer.analyze()
+        };
+
+        let uncached = analyze(Config::default(), None)?;
+        assert!(cache.get(oid).is_none());
+        assert_eq!(analyze(Config::default(), Some(cache.clone()))?, uncached);
+        let cached_commit = cache.get(oid).unwrap();
+        assert_eq!(analyze(Config::default(), Some(cache.clone()))?, uncached);
+
+        // tags, weights and modules are rebuilt from the cache
+        let mut config = Config::default();
+        config.files.insert(
+            "**/*".to_owned(),
+            GlobConfig {
+                tags: vec!["everything".to_owned()],
+                weight: 2.0,
+                ..GlobConfig::default()
+            },
+        );
+        config
+            .modules
+            .insert("docs".to_owned(), vec!["*.md".to_owned()]);
+        assert_eq!(
+            analyze(config.clone(), Some(cache.clone()))?,
+            analyze(config, None)?
+        );
+
+        // the cached classification is used rather than recomputed
+        let mut tampered = cached_commit.clone();
+        tampered.files[0].hunks = vec![WorkStats {
+            new_work: 100,
+            ..WorkStats::empty()
+        }];
+        tampered.files.truncate(1);
+        cache.put(oid, &tampered)?;
+        assert_eq!(
+            analyze(Config::default(), Some(cache.clone()))?
+                .diff
+                .stats
+                .new_work,
+            100
+        );
+
+        // changing the churn cutoff invalidates the cache
+        let mut config = Config::default();
+        config.churn_cutoff = 7;
+        analyze(config, Some(cache.clone()))?;
+        assert_ne!(
+            cache.get(oid).unwrap().classification_hash,
+            cached_commit.classification_hash
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_blame_bug_fixes() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/bug_fixes")?;
+        let oid = repo.head()?.peel_to_commit()?.id();
+        let repo_info = RepoInfo::default();
+        let dir = tempdir::TempDir::new("codealong")?;
+        let cache = Arc::new(AnalysisCache::new(dir.path().to_owned()));
+        let analyze = |blame_bug_fixes: bool| {
+            let commit = repo.find_commit(oid).unwrap();
+            let config = Arc::new(WorkingConfig::default());
+            let mut analyzer =
+                CommitAnalyzer::new(&repo, commit, config, &repo_info, &build_test_logger());
+            analyzer.set_cache(cache.clone());
+            analyzer.set_blame_bug_fixes(blame_bug_fixes);
+            analyzer.analyze()
+        };
+
+        let unblamed = analyze(false)?;
+        assert!(unblamed.bug_fix);
+        assert!(unblamed.bug_introducing_commits.is_empty());
+        assert_eq!(cache.get(oid).unwrap().blamed_lines, None);
+
+        let blamed = analyze(true)?;
+        assert_eq!(blamed.bug_introducing_commits.len(), 1);
+        let mut cached_commit = cache.get(oid).unwrap();
+        assert!(cached_commit.blamed_lines.is_some());
+
+        // the cached blame is used rather than recomputed
+        cached_commit.blamed_lines.as_mut().unwrap()[0].commits = vec!["abc".to_owned()];
+        cache.put(oid, &cached_commit)?;
+        assert_eq!(analyze(true)?.bug_introducing_commits, vec!["abc"]);
         Ok(())
     }
 }
@@ -0,0 +1,118 @@
+use chrono::prelude::*;
+use chrono::DateTime;
+use git2::Commit;
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use crate::analyzed_commit::commit_event_id;
+use crate::event::Event;
+use crate::utils::convert_time;
+
+/// An update to a previously analyzed commit, setting the fields of `fields`
+/// and leaving the others as they are.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommitUpdate<T> {
+    pub id: String,
+
+    #[serde(skip_serializing)]
+    pub authored_at: DateTime<Utc>,
+
+    /// The project of a monorepo whose part of the commit is updated.
+    #[serde(skip)]
+    pub project: Option<String>,
+
+    #[serde(flatten)]
+    pub fields: T,
+}
+
+/// Marks a commit as reverted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reverted {
+    pub reverted_by: String,
+}
+
+/// Marks a commit as having introduced a bug fixed by the commits in
+/// `fixed_by`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BugIntroducing {
+    pub bug_introducing: bool,
+    pub fixed_by: Vec<String>,
+}
+
+/// Sets when a commit was first released and its lead time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Released {
+    pub released_at: DateTime<Utc>,
+    pub lead_time_seconds: i64,
+}
+
+/// Links a commit to the pull request it went through.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reviewed {
+    pub pull_request_number: u64,
+    pub reviewed: bool,
+}
+
+impl<T> CommitUpdate<T> {
+    pub fn new(commit: &Commit, fields: T) -> CommitUpdate<T> {
+        CommitUpdate {
+            id: commit.id().to_string(),
+            authored_at: convert_time(&commit.author().when()),
+            project: None,
+            fields,
+        }
+    }
+}
+
+impl Reverted {
+    pub fn new(reverted_by: &str) -> Reverted {
+        Reverted {
+            reverted_by: reverted_by.to_owned(),
+        }
+    }
+}
+
+impl BugIntroducing {
+    pub fn new(fixed_by: &str) -> BugIntroducing {
+        BugIntroducing {
+            bug_introducing: true,
+            fixed_by: vec![fixed_by.to_owned()],
+        }
+    }
+}
+
+impl Released {
+    pub fn new(released_at: DateTime<Utc>, authored_at: DateTime<Utc>) -> Released {
+        Released {
+            released_at,
+            lead_time_seconds: (released_at - authored_at).num_seconds(),
+        }
+    }
+}
+
+impl Reviewed {
+    pub fn new(pull_request_number: u64) -> Reviewed {
+        Reviewed {
+            pull_request_number,
+            reviewed: true,
+        }
+    }
+}
+
+impl<T> Event for CommitUpdate<T> {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.authored_at
+    }
+
+    fn event_type(&self) -> &str {
+        "commit"
+    }
+
+    fn id(&self) -> Cow<str> {
+        commit_event_id(&self.id, self.project.as_ref())
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        HashSet::new()
+    }
+}
@@ -1,7 +1,9 @@
 use std::fs::File;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+use chrono::Weekday;
 use linked_hash_map::LinkedHashMap;
+use serde::de::{self, Deserialize, Deserializer};
 use serde_yaml;
 
 use crate::contributor::Contributor;
@@ -19,8 +21,21 @@ static BASE_CONFIGS: Dir = include_dir!("./config");
 /// github: ghempton/codealong
 /// churn_cutoff: 14
 ///
+/// bug_fix_patterns:
+///   - "(?i)\\bfix(e[sd])?\\b"
+///   - "\\bBUG-\\d+\\b"
+///
 /// merge_defaults: true
 ///
+/// extends:
+///   - "../shared/languages.yml"
+///   - "teams/*.yml"
+///
+/// merge_strategy:
+///   files: override
+///   contributors: append
+///   modules: append
+///
 /// files:
 ///   "**/*.rb":
 ///     tags:
@@ -37,6 +52,33 @@ static BASE_CONFIGS: Dir = include_dir!("./config");
 ///       - "styles"
 ///       - "css"
 ///     weight: 0.5
+///   "regex:^db/migrate/\\d+_.*\\.rb$":
+///     tags:
+///       - "migration"
+///   "!vendor/**":
+///     tags:
+///       - "first-party"
+///   "**/readme*":
+///     case_insensitive: true
+///     tags:
+///       - "docs"
+///
+/// modules:
+///   api:
+///     - "app/controllers/api"
+///     - "lib/**/api/**"
+///   frontend:
+///     - "app/javascript"
+/// module_depth: 1
+///
+/// working_hours:
+///   start: 9
+///   end: 18
+///   days: ["mon", "tue", "wed", "thu", "fri"]
+/// team_working_hours:
+///   support:
+///     start: 22
+///     end: 6
 ///
 /// authors:
 ///   "Gordon Hempton <ghempton@gmail.com>":
@@ -50,26 +92,133 @@ pub struct Config {
     #[serde(default = "Config::default_merge_defaults")]
     pub merge_defaults: bool,
 
-    #[serde(default = "Config::default_churn_cutoff")]
+    #[serde(
+        default = "Config::default_churn_cutoff",
+        skip_serializing_if = "Config::is_default_churn_cutoff"
+    )]
     pub churn_cutoff: u64,
 
+    /// Regular expressions matched against commit messages to find the
+    /// commits fixing bugs, e.g. by keywords or issue keys.
+    #[serde(default = "Config::default_bug_fix_patterns")]
+    pub bug_fix_patterns: Vec<String>,
+
     #[serde(default)]
     pub files: LinkedHashMap<String, GlobConfig>,
 
     #[serde(default)]
     pub contributors: Vec<ContributorConfig>,
+
+    /// Modules to aggregate work stats by, keyed by name. Each lists path
+    /// prefixes or patterns of its files, and a file belongs to the first
+    /// module matching it.
+    #[serde(default)]
+    pub modules: LinkedHashMap<String, Vec<String>>,
+
+    /// Files outside of `modules` belong to the module named after their
+    /// first this many directories, e.g. `src/bin` for `src/bin/main.rs` and
+    /// a depth of 2. Files in the root directory belong to none.
+    #[serde(default)]
+    pub module_depth: Option<usize>,
+
+    /// The working hours of contributors without their own and whose teams
+    /// have none, in their local time. 9 to 18 from Monday to Friday if unset.
+    #[serde(default)]
+    pub working_hours: Option<WorkingHours>,
+
+    /// Working hours by team.
+    #[serde(default)]
+    pub team_working_hours: LinkedHashMap<String, WorkingHours>,
+
+    /// Paths or globs of other config files this config builds on. Relative
+    /// paths are resolved against the directory containing this config.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extends: Vec<String>,
+
+    /// How this config is layered on top of the configs it is merged into.
+    #[serde(default, skip_serializing_if = "MergeStrategy::is_default")]
+    pub merge_strategy: MergeStrategy,
+
+    /// Configs committed in subdirectories of a repository, keyed by their
+    /// directory relative to the repository root. Only their `files` section
+    /// is used, and its globs are relative to that directory.
+    #[serde(skip)]
+    pub directories: LinkedHashMap<String, Config>,
 }
 
 impl Config {
+    /// Reads the config at `path` and resolves its `extends` entries.
     pub fn from_path(path: &Path) -> Result<Self> {
-        let file = File::open(path)?;
-        Self::from_file(&file)
+        let mut config = Self::read_path(path)?;
+        config.resolve_extends_of(path)?;
+        Ok(config)
     }
 
+    /// Reads the config without resolving `extends` entries.
     pub fn from_file(file: &File) -> Result<Self> {
         Ok(serde_yaml::from_reader::<_, Config>(file)?)
     }
 
+    /// Parses a config from raw YAML, e.g. the contents of a git blob.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        Ok(serde_yaml::from_slice::<Config>(bytes)?)
+    }
+
+    fn read_path(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        Self::from_file(&file)
+    }
+
+    /// Replaces the `extends` entries with the contents of the configs they
+    /// point to. Each extended config is merged in the order listed, after
+    /// which this config is layered on top using its own merge strategy.
+    /// Scalar settings such as `churn_cutoff` are always taken from this
+    /// config, whereas optional ones such as `module_depth` fall back to the
+    /// extended configs when unset.
+    pub fn resolve_extends(&mut self, dir: &Path) -> Result<()> {
+        self.resolve_extends_from(dir, &mut Vec::new())
+    }
+
+    /// Same as `resolve_extends`, but for a config which was read from
+    /// `path`, so that extending the file itself is detected as a cycle.
+    pub(crate) fn resolve_extends_of(&mut self, path: &Path) -> Result<()> {
+        let path = path.canonicalize()?;
+        let dir = path.parent().unwrap().to_owned();
+        self.resolve_extends_from(&dir, &mut vec![path])
+    }
+
+    fn resolve_extends_from(&mut self, dir: &Path, visited: &mut Vec<PathBuf>) -> Result<()> {
+        if self.extends.is_empty() {
+            return Ok(());
+        }
+        let mut res = Config {
+            merge_defaults: self.merge_defaults,
+            churn_cutoff: self.churn_cutoff,
+            bug_fix_patterns: self.bug_fix_patterns.clone(),
+            module_depth: self.module_depth,
+            working_hours: self.working_hours.clone(),
+            ..Config::default()
+        };
+        for pattern in std::mem::take(&mut self.extends) {
+            for path in expand_extends_pattern(dir, &pattern)? {
+                let path = path.canonicalize()?;
+                if visited.contains(&path) {
+                    return Err(ErrorKind::ConfigCycle(path.to_string_lossy().to_string()).into());
+                }
+                let mut parent = Self::read_path(&path)?;
+                visited.push(path.clone());
+                parent.resolve_extends_from(path.parent().unwrap(), visited)?;
+                visited.pop();
+                res.merge(parent);
+            }
+        }
+        let child = std::mem::replace(self, res);
+        let merge_strategy = child.merge_strategy;
+        self.merge(child);
+        self.merge_strategy = merge_strategy;
+        Ok(())
+    }
+
     /// Base config with embedded defaults
     pub fn base() -> Self {
         let mut config = Config::default();
@@ -87,10 +236,68 @@ impl Config {
         14
     }
 
-    /// Merges in all file and author configs
+    fn is_default_churn_cutoff(churn_cutoff: &u64) -> bool {
+        *churn_cutoff == Self::default_churn_cutoff()
+    }
+
+    fn default_bug_fix_patterns() -> Vec<String> {
+        vec![r"(?i)\b(fix(e[sd])?|bug|hotfix)\b".to_owned()]
+    }
+
+    /// Layers the file, contributor and module configs of `other` on top of
+    /// this config, section by section, according to `other.merge_strategy`.
+    /// The `bug_fix_patterns` of both are combined, and `module_depth` and
+    /// `working_hours` are taken from `other` when set. Configs of the same
+    /// directory are merged the same way.
     pub fn merge(&mut self, other: Config) {
-        self.files.extend(other.files);
-        self.contributors.extend(other.contributors);
+        let strategy = other.merge_strategy;
+        merge_map(&mut self.files, other.files, strategy.files);
+        match strategy.contributors {
+            MergeMode::Append => self.contributors.extend(other.contributors),
+            MergeMode::Override => {
+                for contributor_config in other.contributors {
+                    match self
+                        .contributors
+                        .iter_mut()
+                        .find(|c| c.contributor.id == contributor_config.contributor.id)
+                    {
+                        Some(existing) => *existing = contributor_config,
+                        None => self.contributors.push(contributor_config),
+                    }
+                }
+            }
+            MergeMode::Replace => self.contributors = other.contributors,
+        }
+        for pattern in other.bug_fix_patterns {
+            if !self.bug_fix_patterns.contains(&pattern) {
+                self.bug_fix_patterns.push(pattern);
+            }
+        }
+        merge_map(&mut self.modules, other.modules, strategy.modules);
+        if other.module_depth.is_some() {
+            self.module_depth = other.module_depth;
+        }
+        if other.working_hours.is_some() {
+            self.working_hours = other.working_hours;
+        }
+        self.team_working_hours.extend(other.team_working_hours);
+        for (dir, dir_config) in other.directories {
+            match self.directories.get_mut(&dir) {
+                // configs of the same directory are layered like any other,
+                // and replace the configs of enclosing directories if either
+                // does
+                Some(existing) => {
+                    let replace = dir_config.merge_strategy.files == MergeMode::Replace;
+                    existing.merge(dir_config);
+                    if replace {
+                        existing.merge_strategy.files = MergeMode::Replace;
+                    }
+                }
+                None => {
+                    self.directories.insert(dir, dir_config);
+                }
+            }
+        }
     }
 
     /// Merges contributors based on name and email matches. The contributor
@@ -124,17 +331,111 @@ impl Config {
     }
 }
 
+/// Merges the entries of `other` into `map` according to `mode`.
+fn merge_map<V>(
+    map: &mut LinkedHashMap<String, V>,
+    other: LinkedHashMap<String, V>,
+    mode: MergeMode,
+) {
+    match mode {
+        MergeMode::Append => map.extend(other),
+        MergeMode::Override => {
+            for (key, value) in other {
+                if let Some(existing) = map.get_mut(&key) {
+                    *existing = value;
+                    continue;
+                }
+                map.insert(key, value);
+            }
+        }
+        MergeMode::Replace => *map = other,
+    }
+}
+
 impl Default for Config {
     fn default() -> Config {
         Config {
             merge_defaults: true,
             churn_cutoff: 14,
+            bug_fix_patterns: Config::default_bug_fix_patterns(),
             files: LinkedHashMap::new(),
             contributors: Vec::new(),
+            modules: LinkedHashMap::new(),
+            module_depth: None,
+            working_hours: None,
+            team_working_hours: LinkedHashMap::new(),
+            extends: Vec::new(),
+            merge_strategy: MergeStrategy::default(),
+            directories: LinkedHashMap::new(),
         }
     }
 }
 
+/// Expands a single `extends` entry into the config paths it refers to.
+/// Plain paths must exist, whereas globs may match nothing.
+fn expand_extends_pattern(dir: &Path, pattern: &str) -> Result<Vec<PathBuf>> {
+    let path = dir.join(pattern);
+    if !pattern.contains(&['*', '?', '['][..]) {
+        return Ok(vec![path]);
+    }
+    let mut paths = Vec::new();
+    for entry in glob::glob(&path.to_string_lossy())? {
+        paths.push(entry?);
+    }
+    Ok(paths)
+}
+
+/// Per-section merge behavior used when one config is layered on top of
+/// another.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MergeStrategy {
+    #[serde(default)]
+    pub files: MergeMode,
+
+    #[serde(default)]
+    pub contributors: MergeMode,
+
+    #[serde(default)]
+    pub modules: MergeMode,
+}
+
+impl MergeStrategy {
+    fn is_default(&self) -> bool {
+        *self == MergeStrategy::default()
+    }
+}
+
+impl Default for MergeStrategy {
+    fn default() -> MergeStrategy {
+        MergeStrategy {
+            files: MergeMode::Append,
+            contributors: MergeMode::Append,
+            modules: MergeMode::Append,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeMode {
+    /// Entries are added after the existing ones. A glob which is already
+    /// present is moved to the end so that its weight takes precedence.
+    Append,
+
+    /// Entries with the same glob or contributor id are replaced in place,
+    /// anything else is appended.
+    Override,
+
+    /// The section replaces the existing section entirely.
+    Replace,
+}
+
+impl Default for MergeMode {
+    fn default() -> MergeMode {
+        MergeMode::Append
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GlobConfig {
     #[serde(default)]
@@ -145,6 +446,10 @@ pub struct GlobConfig {
 
     #[serde(default)]
     pub ignore: bool,
+
+    /// Match the pattern without regard to ASCII case.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub case_insensitive: bool,
 }
 
 impl GlobConfig {
@@ -153,6 +458,17 @@ impl GlobConfig {
     }
 }
 
+impl Default for GlobConfig {
+    fn default() -> GlobConfig {
+        GlobConfig {
+            tags: vec![],
+            weight: 1.0,
+            ignore: false,
+            case_insensitive: false,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ContributorConfig {
     #[serde(flatten)]
@@ -163,6 +479,10 @@ pub struct ContributorConfig {
 
     #[serde(default)]
     pub ignore: bool,
+
+    /// Overrides the working hours of the contributor's teams.
+    #[serde(default)]
+    pub working_hours: Option<WorkingHours>,
 }
 
 impl Default for ContributorConfig {
@@ -171,10 +491,87 @@ impl Default for ContributorConfig {
             contributor: Default::default(),
             tags: vec![],
             ignore: false,
+            working_hours: None,
+        }
+    }
+}
+
+/// The hours of the day and days of the week someone usually works, in their
+/// local time. Hours are from `start` until `end`, which wrap around midnight
+/// if `end` is before `start`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkingHours {
+    #[serde(
+        default = "WorkingHours::default_start",
+        deserialize_with = "deserialize_hour"
+    )]
+    pub start: u32,
+
+    #[serde(
+        default = "WorkingHours::default_end",
+        deserialize_with = "deserialize_hour"
+    )]
+    pub end: u32,
+
+    /// Configured as abbreviated or full weekday names, e.g. `mon` or
+    /// `Monday`.
+    #[serde(default = "WorkingHours::default_days")]
+    pub days: Vec<Weekday>,
+}
+
+impl WorkingHours {
+    fn default_start() -> u32 {
+        9
+    }
+
+    fn default_end() -> u32 {
+        18
+    }
+
+    fn default_days() -> Vec<Weekday> {
+        vec![
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+        ]
+    }
+
+    pub fn is_working_day(&self, weekday: Weekday) -> bool {
+        self.days.contains(&weekday)
+    }
+
+    pub fn is_working_hour(&self, hour: u32) -> bool {
+        if self.start <= self.end {
+            hour >= self.start && hour < self.end
+        } else {
+            hour >= self.start || hour < self.end
+        }
+    }
+}
+
+impl Default for WorkingHours {
+    fn default() -> WorkingHours {
+        WorkingHours {
+            start: WorkingHours::default_start(),
+            end: WorkingHours::default_end(),
+            days: WorkingHours::default_days(),
         }
     }
 }
 
+fn deserialize_hour<'de, D>(deserializer: D) -> std::result::Result<u32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let hour = u32::deserialize(deserializer)?;
+    if hour > 23 {
+        return Err(de::Error::custom(format!("invalid hour {}", hour)));
+    }
+    Ok(hour)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -196,10 +593,12 @@ mod tests {
                 weight: 1.0,
                 ignore: false,
                 tags: vec!["ruby".to_string()],
+                ..GlobConfig::default()
             },
         );
 
         let mut config2 = Config::default();
+        config2.bug_fix_patterns.push(r"\bBUG-\d+\b".to_owned());
 
         config2.files.insert(
             "**/*.rs".to_string(),
@@ -207,12 +606,137 @@ mod tests {
                 weight: 1.0,
                 ignore: false,
                 tags: vec!["rust".to_string()],
+                ..GlobConfig::default()
             },
         );
 
         config.merge(config2);
 
         assert!(config.files.keys().len() == 2);
+        assert_eq!(
+            config.bug_fix_patterns,
+            vec![
+                r"(?i)\b(fix(e[sd])?|bug|hotfix)\b".to_owned(),
+                r"\bBUG-\d+\b".to_owned()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_strategies() {
+        let build = |tag: &str, id: &str| {
+            let mut config = Config::default();
+            config.files.insert(
+                "**/*.rb".to_string(),
+                GlobConfig {
+                    weight: 1.0,
+                    ignore: false,
+                    tags: vec![tag.to_string()],
+                    ..GlobConfig::default()
+                },
+            );
+            config.contributors.push(ContributorConfig {
+                contributor: Contributor {
+                    id: id.to_owned(),
+                    ..Contributor::default()
+                },
+                ..ContributorConfig::default()
+            });
+            config
+        };
+
+        let mut config = build("ruby", "a");
+        let mut other = build("rails", "a");
+        other.merge_strategy.contributors = MergeMode::Override;
+        config.merge(other);
+        assert_eq!(config.files.get("**/*.rb").unwrap().tags, vec!["rails"]);
+        assert_eq!(config.contributors.len(), 1);
+
+        let mut config = build("ruby", "a");
+        let mut other = build("rails", "b");
+        other.files.clear();
+        other.merge_strategy = MergeStrategy {
+            files: MergeMode::Replace,
+            ..MergeStrategy::default()
+        };
+        config.merge(other);
+        assert!(config.files.is_empty());
+        assert_eq!(config.contributors.len(), 2);
+    }
+
+    #[test]
+    fn test_merge_modules() {
+        let mut config = Config::default();
+        config.module_depth = Some(1);
+        config
+            .modules
+            .insert("api".to_owned(), vec!["src/api/".to_owned()]);
+        config
+            .modules
+            .insert("web".to_owned(), vec!["web/".to_owned()]);
+
+        let mut other = Config::default();
+        other
+            .modules
+            .insert("api".to_owned(), vec!["api/".to_owned()]);
+        other.merge_strategy.modules = MergeMode::Override;
+        config.merge(other);
+        assert_eq!(config.module_depth, Some(1));
+        assert_eq!(
+            config.modules.keys().collect::<Vec<_>>(),
+            vec!["api", "web"]
+        );
+        assert_eq!(config.modules["api"], vec!["api/"]);
+
+        let mut other = Config::default();
+        other.module_depth = Some(2);
+        other
+            .modules
+            .insert("docs".to_owned(), vec!["docs/".to_owned()]);
+        other.merge_strategy.modules = MergeMode::Replace;
+        config.merge(other);
+        assert_eq!(config.module_depth, Some(2));
+        assert_eq!(config.modules.keys().collect::<Vec<_>>(), vec!["docs"]);
+    }
+
+    #[test]
+    fn test_merge_working_hours() {
+        let night_shift = WorkingHours {
+            start: 22,
+            end: 6,
+            ..WorkingHours::default()
+        };
+        let mut config = Config::default();
+        let mut other = Config::default();
+        other.working_hours = Some(night_shift.clone());
+        config.merge(other);
+        assert_eq!(config.working_hours, Some(night_shift.clone()));
+
+        config.merge(Config::default());
+        assert_eq!(config.working_hours, Some(night_shift));
+    }
+
+    #[test]
+    fn test_extends() -> Result<()> {
+        let config = Config::from_path(Path::new("fixtures/configs/extends/child.yml"))?;
+        assert!(config.extends.is_empty());
+        assert_eq!(
+            config.files.keys().collect::<Vec<_>>(),
+            vec!["**/*.rb", "**/*.css", "**/*.js"]
+        );
+        assert_eq!(config.files.get("**/*.rb").unwrap().weight, 2.0);
+        assert_eq!(config.contributors.len(), 1);
+        assert_eq!(config.churn_cutoff, 14);
+        assert_eq!(config.merge_strategy.files, MergeMode::Override);
+        Ok(())
+    }
+
+    #[test]
+    fn test_extends_cycle() {
+        match Config::from_path(Path::new("fixtures/configs/extends/cycle_a.yml")) {
+            Err(Error(ErrorKind::ConfigCycle(_), _)) => (),
+            res => panic!("expected cycle error, got {:?}", res),
+        }
     }
 
     #[test]
@@ -268,4 +792,22 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_working_hours() {
+        let working_hours = WorkingHours::default();
+        assert!(working_hours.is_working_day(Weekday::Fri));
+        assert!(!working_hours.is_working_day(Weekday::Sat));
+        assert!(working_hours.is_working_hour(9));
+        assert!(!working_hours.is_working_hour(18));
+        let night_shift: WorkingHours =
+            serde_yaml::from_str("{start: 22, end: 6, days: [Saturday, sun]}").unwrap();
+        assert!(night_shift.is_working_day(Weekday::Sat));
+        assert!(night_shift.is_working_day(Weekday::Sun));
+        assert!(night_shift.is_working_hour(23));
+        assert!(night_shift.is_working_hour(2));
+        assert!(!night_shift.is_working_hour(12));
+        assert!(serde_yaml::from_str::<WorkingHours>("{days: [mon, someday]}").is_err());
+        assert!(serde_yaml::from_str::<WorkingHours>("{end: 24}").is_err());
+    }
 }
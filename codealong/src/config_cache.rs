@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use git2::{Commit, Oid, Repository};
+
+use crate::config::Config;
+use crate::error::*;
+use crate::project::{Project, ProjectConfig};
+use crate::repo_config::RepoConfig;
+use crate::working_config::WorkingConfig;
+
+/// Provides the config that was in effect at a given commit by layering the
+/// `.codealong.yml` files committed in that commit's tree on top of a base
+/// config.
+///
+/// Working configs are cached by the blob ids of the in-repo configs, so each
+/// distinct combination of files is only parsed once per analysis. The same
+/// goes for the configs of the projects of a monorepo, which are layered on
+/// top of the config of the commit.
+pub struct ConfigCache {
+    base_config: Config,
+    project_configs: Vec<ProjectConfig>,
+    without_repo_config: (Arc<WorkingConfig>, Arc<Vec<Project>>),
+    configs: HashMap<(Option<Oid>, Vec<(String, Oid)>), (Arc<WorkingConfig>, Arc<Vec<Project>>)>,
+    directory_configs: HashMap<Oid, Vec<(String, Oid)>>,
+}
+
+impl ConfigCache {
+    pub fn new(base_config: Config, project_configs: Vec<ProjectConfig>) -> ConfigCache {
+        ConfigCache {
+            without_repo_config: build_configs(base_config.clone(), &project_configs),
+            base_config,
+            project_configs,
+            configs: HashMap::new(),
+            directory_configs: HashMap::new(),
+        }
+    }
+
+    pub fn config_for_commit(
+        &mut self,
+        repo: &Repository,
+        commit: &Commit,
+    ) -> Result<Arc<WorkingConfig>> {
+        Ok(self.configs_for_commit(repo, commit)?.0)
+    }
+
+    /// The config in effect at `commit` along with the projects built on top
+    /// of it.
+    pub fn configs_for_commit(
+        &mut self,
+        repo: &Repository,
+        commit: &Commit,
+    ) -> Result<(Arc<WorkingConfig>, Arc<Vec<Project>>)> {
+        let tree = commit.tree()?;
+        let oid = RepoConfig::find_config_blob(&tree);
+        let directories =
+            RepoConfig::find_directory_config_blobs(repo, &tree, &mut self.directory_configs)?;
+        if oid.is_none() && directories.is_empty() {
+            return Ok(self.without_repo_config.clone());
+        }
+        let key = (oid, directories);
+        if let Some(config) = self.configs.get(&key) {
+            return Ok(config.clone());
+        }
+        let repo_config = match oid {
+            Some(oid) => RepoConfig::config_from_blob(repo, oid)?,
+            None => Config::default(),
+        };
+        let mut config = self.base_config.clone();
+        config.merge(RepoConfig::with_directories(repo, repo_config, &key.1)?);
+        let configs = build_configs(config, &self.project_configs);
+        self.configs.insert(key, configs.clone());
+        Ok(configs)
+    }
+}
+
+fn build_configs(
+    config: Config,
+    project_configs: &[ProjectConfig],
+) -> (Arc<WorkingConfig>, Arc<Vec<Project>>) {
+    let projects = project_configs
+        .iter()
+        .map(|project_config| Project::new(project_config, &config))
+        .collect();
+    (Arc::new(WorkingConfig::new(config)), Arc::new(projects))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config::GlobConfig;
+
+    #[test]
+    fn test_config_for_commit() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/historical_config")?;
+        let mut cache = ConfigCache::new(Config::default(), Vec::new());
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push_head()?;
+        let mut configs = Vec::new();
+        for oid in revwalk {
+            let commit = repo.find_commit(oid?)?;
+            configs.push(cache.config_for_commit(&repo, &commit)?);
+        }
+        let tags: Vec<Vec<String>> = configs
+            .iter()
+            .map(|config| {
+                let file_config = config.config_for_file("README.md").unwrap();
+                file_config.tags().iter().map(|s| s.to_string()).collect()
+            })
+            .collect();
+        assert_eq!(
+            tags,
+            vec![vec!["docs-v2"], vec!["docs-v2"], vec!["docs-v1"]]
+        );
+        assert!(Arc::ptr_eq(&configs[0], &configs[1]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_projects_for_commit() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/historical_config")?;
+        let project_config = ProjectConfig {
+            name: "docs".to_owned(),
+            path: "docs".to_owned(),
+            tags: vec!["docs".to_owned()],
+            churn_cutoff: None,
+            config: Config::default(),
+        };
+        let mut cache = ConfigCache::new(Config::default(), vec![project_config]);
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push_head()?;
+        let mut tags = Vec::new();
+        for oid in revwalk {
+            let commit = repo.find_commit(oid?)?;
+            let (_, projects) = cache.configs_for_commit(&repo, &commit)?;
+            let file_config = projects[0]
+                .config()
+                .config_for_file("docs/guide.md")
+                .unwrap();
+            let mut file_tags: Vec<String> =
+                file_config.tags().iter().map(|s| s.to_string()).collect();
+            file_tags.sort();
+            tags.push(file_tags);
+        }
+        // the project's tags are added to those of the config of each commit
+        assert_eq!(
+            tags,
+            vec![
+                vec!["docs", "docs-v2"],
+                vec!["docs", "docs-v2"],
+                vec!["docs", "docs-v1"]
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_projects_with_directory_configs() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/nested_config")?;
+        let mut project_config = ProjectConfig {
+            name: "api".to_owned(),
+            path: "services/api".to_owned(),
+            tags: vec!["api".to_owned()],
+            churn_cutoff: Some(30),
+            config: Config::default(),
+        };
+        project_config.config.files.insert(
+            "app.rb".to_owned(),
+            GlobConfig {
+                tags: vec!["app".to_owned()],
+                ..GlobConfig::default()
+            },
+        );
+        let mut cache = ConfigCache::new(Config::default(), vec![project_config]);
+        let commit = repo.head()?.peel_to_commit()?;
+        let (config, projects) = cache.configs_for_commit(&repo, &commit)?;
+        let project_config = projects[0].config();
+        assert_eq!(config.churn_cutoff(), 14);
+        assert_eq!(project_config.churn_cutoff(), 30);
+        // the config committed in the project's directory still applies
+        let tags = |path: &str| {
+            let file_config = project_config.config_for_file(path).unwrap();
+            let mut tags: Vec<String> = file_config.tags().iter().map(|s| s.to_string()).collect();
+            tags.sort();
+            tags
+        };
+        assert_eq!(
+            tags("services/api/app.rb"),
+            vec!["api", "app", "ruby", "team-api"]
+        );
+        assert_eq!(tags("services/api/lib.rb"), vec!["api", "ruby", "team-api"]);
+        assert!(project_config
+            .config_for_file("services/api/vendor/lib.rb")
+            .unwrap()
+            .ignore());
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+use chrono::prelude::*;
+use chrono::DateTime;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::event::Event;
+use crate::repo_info::PartialRepoInfo;
+
+/// Options for `RepoAnalyzer::analyze_coupling`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CouplingOpts {
+    /// Only changes committed since are counted. All are if not set.
+    pub since: Option<DateTime<Utc>>,
+
+    /// Count the commits of a pull request as a single change.
+    pub by_pull_request: bool,
+
+    /// The pull request each commit id belongs to according to GitHub, in
+    /// addition to those found in commit messages.
+    pub pull_request_commits: BTreeMap<String, u64>,
+
+    /// The number of changes a pair needs to have changed together in.
+    pub min_support: usize,
+
+    /// The fraction of the changes of either one of a pair which need to
+    /// have changed the other as well.
+    pub min_confidence: f64,
+
+    /// Changes touching more files, such as reformatting the whole code base,
+    /// say nothing about coupling and are skipped.
+    pub max_change_size: usize,
+}
+
+impl Default for CouplingOpts {
+    fn default() -> Self {
+        CouplingOpts {
+            since: None,
+            by_pull_request: false,
+            pull_request_commits: BTreeMap::new(),
+            min_support: 3,
+            min_confidence: 0.5,
+            max_change_size: 50,
+        }
+    }
+}
+
+/// Two files or directories which tend to change together.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Coupling {
+    pub id: String,
+
+    /// The first of the pair in lexicographic order. Empty for the root
+    /// directory.
+    pub path: String,
+    pub coupled_path: String,
+    pub directory: bool,
+
+    pub commit_id: String,
+    pub snapshot_at: DateTime<Utc>,
+    pub since: Option<DateTime<Utc>>,
+
+    /// The number of changes of `path` and `coupled_path`, respectively.
+    pub changes: usize,
+    pub coupled_changes: usize,
+
+    /// The number of changes of both.
+    pub co_changes: usize,
+
+    /// The fraction of the changes of `path` which changed `coupled_path`,
+    /// and vice versa.
+    pub confidence: f64,
+    pub coupled_confidence: f64,
+
+    /// The number of changes of both relative to the average number of
+    /// changes of each.
+    pub strength: f64,
+
+    pub repo: Option<PartialRepoInfo>,
+}
+
+impl Event for Coupling {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.snapshot_at
+    }
+
+    fn event_type(&self) -> &str {
+        "coupling"
+    }
+
+    fn id(&self) -> Cow<str> {
+        Cow::Borrowed(&self.id)
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        HashSet::new()
+    }
+}
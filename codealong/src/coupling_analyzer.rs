@@ -0,0 +1,259 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use git2::{Commit, Repository, Sort};
+
+use crate::coupling::{Coupling, CouplingOpts};
+use crate::diff_analyzer::DiffAnalyzer;
+use crate::error::*;
+use crate::pull_request_links::PullRequestLinks;
+use crate::repo_info::RepoInfo;
+use crate::utils::convert_time;
+use crate::working_config::WorkingConfig;
+
+/// Finds the files and directories which tend to change in the same commits,
+/// or the same pull requests.
+pub struct CouplingAnalyzer<'a> {
+    repo: &'a Repository,
+    config: &'a WorkingConfig,
+    repo_info: &'a RepoInfo,
+}
+
+/// How often each path changed, alone and along with each other path.
+struct CoChanges {
+    changes: HashMap<String, usize>,
+    co_changes: BTreeMap<(String, String), usize>,
+}
+
+impl<'a> CouplingAnalyzer<'a> {
+    pub fn new(
+        repo: &'a Repository,
+        config: &'a WorkingConfig,
+        repo_info: &'a RepoInfo,
+    ) -> CouplingAnalyzer<'a> {
+        CouplingAnalyzer {
+            repo,
+            config,
+            repo_info,
+        }
+    }
+
+    /// Emits the coupling of each pair of files, and each pair of the
+    /// directories directly containing them, changed together in the history
+    /// of `tip` often enough to pass the thresholds. Strongest first. Merge
+    /// commits and ignored files are skipped.
+    pub fn analyze(&self, tip: &Commit, opts: &CouplingOpts) -> Result<Vec<Coupling>> {
+        let change_sets = self.change_sets(tip, opts)?;
+        let mut files = CoChanges::new();
+        let mut directories = CoChanges::new();
+        for paths in change_sets.values() {
+            if paths.len() > opts.max_change_size {
+                continue;
+            }
+            files.add(paths);
+            directories.add(&paths.iter().map(|path| directory_of(path)).collect());
+        }
+        let mut result: Vec<Coupling> = files
+            .couplings(false, tip, opts, self.repo_info)
+            .chain(directories.couplings(true, tip, opts, self.repo_info))
+            .collect();
+        result.sort_by(|a, b| {
+            b.strength
+                .partial_cmp(&a.strength)
+                .unwrap()
+                .then_with(|| a.directory.cmp(&b.directory))
+                .then_with(|| a.path.cmp(&b.path))
+                .then_with(|| a.coupled_path.cmp(&b.coupled_path))
+        });
+        Ok(result)
+    }
+
+    /// The paths changed by each commit within the window, or by each pull
+    /// request if grouping by them.
+    fn change_sets(
+        &self,
+        tip: &Commit,
+        opts: &CouplingOpts,
+    ) -> Result<HashMap<String, BTreeSet<String>>> {
+        let links = if opts.by_pull_request {
+            PullRequestLinks::resolve(self.repo, &[tip.id()], &[], &opts.pull_request_commits)?
+        } else {
+            PullRequestLinks::empty()
+        };
+        let mut result: HashMap<String, BTreeSet<String>> = HashMap::new();
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.set_sorting(Sort::TIME);
+        revwalk.push(tip.id())?;
+        for oid in revwalk {
+            let commit = self.repo.find_commit(oid?)?;
+            if let Some(since) = opts.since {
+                if convert_time(&commit.committer().when()) < since {
+                    continue;
+                }
+            }
+            if commit.parent_count() > 1 {
+                continue;
+            }
+            let key = match links.pull_request_of(commit.id()) {
+                Some(number) => format!("#{}", number),
+                None => commit.id().to_string(),
+            };
+            let parent = commit.parents().next();
+            let diff_analyzer = DiffAnalyzer::new(self.repo, &commit, parent.as_ref(), self.config);
+            let paths = result.entry(key).or_insert_with(BTreeSet::new);
+            for path in diff_analyzer.changed_paths()? {
+                let ignored = self
+                    .config
+                    .config_for_file(&path)
+                    .map(|c| c.ignore())
+                    .unwrap_or(false);
+                if !ignored {
+                    paths.insert(path);
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+impl CoChanges {
+    fn new() -> CoChanges {
+        CoChanges {
+            changes: HashMap::new(),
+            co_changes: BTreeMap::new(),
+        }
+    }
+
+    fn add(&mut self, paths: &BTreeSet<String>) {
+        for path in paths {
+            *self.changes.entry(path.clone()).or_insert(0) += 1;
+        }
+        for (index, path) in paths.iter().enumerate() {
+            for coupled_path in paths.iter().skip(index + 1) {
+                *self
+                    .co_changes
+                    .entry((path.clone(), coupled_path.clone()))
+                    .or_insert(0) += 1;
+            }
+        }
+    }
+
+    fn couplings<'a>(
+        &'a self,
+        directory: bool,
+        tip: &'a Commit,
+        opts: &'a CouplingOpts,
+        repo_info: &'a RepoInfo,
+    ) -> impl Iterator<Item = Coupling> + 'a {
+        self.co_changes
+            .iter()
+            .filter(move |(_, co_changes)| **co_changes >= opts.min_support)
+            .map(move |((path, coupled_path), co_changes)| {
+                let changes = self.changes[path];
+                let coupled_changes = self.changes[coupled_path];
+                Coupling {
+                    id: format!(
+                        "{}:{}:coupling:{}:{}",
+                        repo_info.name,
+                        tip.id(),
+                        path,
+                        coupled_path
+                    ),
+                    path: path.clone(),
+                    coupled_path: coupled_path.clone(),
+                    directory,
+                    commit_id: tip.id().to_string(),
+                    snapshot_at: convert_time(&tip.committer().when()),
+                    since: opts.since,
+                    changes,
+                    coupled_changes,
+                    co_changes: *co_changes,
+                    confidence: *co_changes as f64 / changes as f64,
+                    coupled_confidence: *co_changes as f64 / coupled_changes as f64,
+                    strength: 2.0 * *co_changes as f64 / (changes + coupled_changes) as f64,
+                    repo: Some(repo_info.partial()),
+                }
+            })
+            .filter(move |coupling| {
+                coupling.confidence.max(coupling.coupled_confidence) >= opts.min_confidence
+            })
+    }
+}
+
+/// The directory directly containing `path`. Empty for the root directory.
+fn directory_of(path: &str) -> String {
+    match path.rfind('/') {
+        Some(index) => path[..index].to_owned(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn couplings(opts: CouplingOpts) -> Result<Vec<(bool, String, String, usize, usize, usize)>> {
+        let repo = Repository::open("./fixtures/repos/coupling")?;
+        let config = WorkingConfig::default();
+        let repo_info = RepoInfo::default();
+        let analyzer = CouplingAnalyzer::new(&repo, &config, &repo_info);
+        let tip = repo.head()?.peel_to_commit()?;
+        Ok(analyzer
+            .analyze(&tip, &opts)?
+            .into_iter()
+            .map(|c| {
+                (
+                    c.directory,
+                    c.path,
+                    c.coupled_path,
+                    c.changes,
+                    c.coupled_changes,
+                    c.co_changes,
+                )
+            })
+            .collect())
+    }
+
+    #[test]
+    fn test_analyze() -> Result<()> {
+        let opts = CouplingOpts {
+            min_support: 2,
+            ..CouplingOpts::default()
+        };
+        assert_eq!(
+            couplings(opts)?,
+            vec![
+                (false, "src/a.rs".to_owned(), "src/b.rs".to_owned(), 4, 4, 3),
+                (
+                    false,
+                    "docs/a.md".to_owned(),
+                    "src/a.rs".to_owned(),
+                    3,
+                    4,
+                    2
+                ),
+                (true, "docs".to_owned(), "src".to_owned(), 3, 5, 2),
+            ]
+        );
+
+        // "Change b" and "Document b" were merged through the same pull
+        // request
+        let opts = CouplingOpts {
+            min_support: 2,
+            by_pull_request: true,
+            ..CouplingOpts::default()
+        };
+        let pairs: Vec<(String, String, usize)> = couplings(opts)?
+            .into_iter()
+            .filter(|c| !c.0)
+            .map(|c| (c.1, c.2, c.5))
+            .collect();
+        assert!(pairs.contains(&("docs/a.md".to_owned(), "src/b.rs".to_owned(), 2)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_directory_of() {
+        assert_eq!(directory_of("src/bin/main.rs"), "src/bin");
+        assert_eq!(directory_of("README.md"), "");
+    }
+}
@@ -1,12 +1,12 @@
-use git2::{Commit, Diff, DiffOptions, Repository};
+use git2::{Commit, Diff, DiffOptions, Patch, Repository};
 
 use std::cell::RefCell;
-use std::collections::HashMap;
 
+use crate::analysis_cache::CachedFile;
 use crate::analyzed_diff::AnalyzedDiff;
 use crate::error::Error;
 use crate::file_analyzer::FileAnalyzer;
-use crate::work_stats::WorkStats;
+use crate::submodule_update::SubmodulePaths;
 use crate::working_config::WorkingConfig;
 
 pub struct DiffAnalyzer<'a> {
@@ -32,27 +32,40 @@ impl<'a> DiffAnalyzer<'a> {
     }
 
     pub fn analyze(&self) -> Result<AnalyzedDiff, Error> {
-        let mut result = AnalyzedDiff {
-            tag_stats: HashMap::new(),
-            stats: WorkStats::empty(),
-        };
+        Ok(self.analyze_with_cached_files()?.0)
+    }
+
+    /// Same as `analyze`, but also returns the raw classification of each
+    /// changed file for use in an `AnalysisCache`.
+    pub fn analyze_with_cached_files(&self) -> Result<(AnalyzedDiff, Vec<CachedFile>), Error> {
+        let mut result = AnalyzedDiff::empty();
+        let mut cached_files = Vec::new();
         let file_analyzer: RefCell<Option<FileAnalyzer>> = RefCell::new(None);
         let diff = self.build_diff()?;
+        let submodule_paths = SubmodulePaths::new(self.repo, self.commit, self.parent)?;
         diff.foreach(
             &mut |diff_delta, _| {
                 if let Some(file_analyzer) = file_analyzer.borrow_mut().take() {
-                    result += file_analyzer.finish();
+                    let (file_result, cached_file) = file_analyzer.finish();
+                    result += file_result;
+                    cached_files.push(cached_file);
                 }
                 file_analyzer.replace(Some(FileAnalyzer::new(
                     self.repo,
                     self.commit,
                     self.parent,
                     &diff_delta,
+                    &submodule_paths,
                     self.config,
                 )));
                 true
             },
-            None,
+            Some(&mut |diff_delta, _diff_binary| {
+                let mut inner = file_analyzer.borrow_mut().take().unwrap();
+                inner.set_binary(&diff_delta);
+                file_analyzer.replace(Some(inner));
+                true
+            }),
             Some(&mut |_diff_delta, _diff_hunk| {
                 let mut inner = file_analyzer.borrow_mut().take().unwrap();
                 inner.start_hunk().expect("unable to start hunk");
@@ -70,7 +83,51 @@ impl<'a> DiffAnalyzer<'a> {
             }),
         )?;
         if let Some(file_analyzer) = file_analyzer.borrow_mut().take() {
-            result += file_analyzer.finish();
+            let (file_result, cached_file) = file_analyzer.finish();
+            result += file_result;
+            cached_files.push(cached_file);
+        }
+        Ok((result, cached_files))
+    }
+
+    /// The paths of the files changed, without analyzing their lines. Paths
+    /// which are not valid UTF-8 are converted lossily.
+    pub fn changed_paths(&self) -> Result<Vec<String>, Error> {
+        let diff = self.build_diff()?;
+        Ok(diff
+            .deltas()
+            .filter_map(|diff_delta| {
+                diff_delta
+                    .new_file()
+                    .path_bytes()
+                    .or(diff_delta.old_file().path_bytes())
+                    .map(|path| String::from_utf8_lossy(path).into_owned())
+            })
+            .collect())
+    }
+
+    /// The number of lines added and deleted in each changed file, without
+    /// classifying them. Added lines are the ones `analyze` counts as new
+    /// work. Paths which are not valid UTF-8 are converted lossily.
+    pub fn line_stats(&self) -> Result<Vec<(String, usize, usize)>, Error> {
+        let diff = self.build_diff()?;
+        let mut result = Vec::new();
+        for index in 0..diff.deltas().len() {
+            let patch = match Patch::from_diff(&diff, index)? {
+                Some(patch) => patch,
+                None => continue,
+            };
+            let path = match patch
+                .delta()
+                .new_file()
+                .path_bytes()
+                .or(patch.delta().old_file().path_bytes())
+            {
+                Some(path) => String::from_utf8_lossy(path).into_owned(),
+                None => continue,
+            };
+            let (_, additions, deletions) = patch.line_stats()?;
+            result.push((path, additions, deletions));
         }
         Ok(result)
     }
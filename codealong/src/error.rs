@@ -1,5 +1,6 @@
 #![allow(deprecated)]
 use git2;
+use glob;
 use serde_yaml;
 use std::io;
 
@@ -13,11 +14,21 @@ error_chain! {
             description("error running git blame")
             display("blame error: {}", message)
         }
+        ConfigCycle(path: String) {
+            description("config extends itself")
+            display("config extends itself: '{}'", path)
+        }
+        WorkerPanicked(commit: String) {
+            description("analysis worker panicked")
+            display("analysis worker panicked while analyzing commit: '{}'", commit)
+        }
     }
 
     foreign_links {
         Git2(git2::Error);
         IO(io::Error);
         Config(serde_yaml::Error);
+        Pattern(glob::PatternError);
+        Glob(glob::GlobError);
     }
 }
@@ -1,21 +1,33 @@
+use std::path::{Path, PathBuf};
+
 use git2::{Commit, Delta, DiffDelta, DiffLine, Repository};
 
+use crate::analysis_cache::CachedFile;
 use crate::analyzed_diff::AnalyzedDiff;
+use crate::binary_stats::BinaryStats;
 use crate::config::ContributorConfig;
 use crate::config_context::ConfigContext;
 use crate::error::Error;
 use crate::git_blame::GitBlame;
 use crate::hunk_analyzer::HunkAnalyzer;
+use crate::submodule_update::{SubmodulePaths, SubmoduleUpdate};
+use crate::work_stats::WorkStats;
 use crate::working_config::{FileConfig, WorkingConfig};
 
 pub struct FileAnalyzer<'a> {
     repo: &'a Repository,
     commit: &'a Commit<'a>,
+    parent: Option<&'a Commit<'a>>,
+    config: &'a WorkingConfig,
     result: AnalyzedDiff,
     blame: Option<GitBlame>,
+    blame_path: Option<PathBuf>,
     config_context: ConfigContext,
     current_hunk: Option<HunkAnalyzer<'a>>,
     ignored: bool,
+    module: Option<String>,
+    submodule: Option<SubmoduleUpdate>,
+    cached_file: CachedFile,
 }
 
 impl<'a> FileAnalyzer<'a> {
@@ -24,26 +36,66 @@ impl<'a> FileAnalyzer<'a> {
         commit: &'a Commit<'a>,
         parent: Option<&'a Commit<'a>>,
         diff_delta: &DiffDelta,
+        submodule_paths: &SubmodulePaths,
         config: &'a WorkingConfig,
     ) -> FileAnalyzer<'a> {
         let file_config = get_file_config(config, &diff_delta);
         let author_config = get_author_config(config, commit);
         let config_context = ConfigContext::new(file_config.as_ref(), author_config);
-        let blame = get_blame(repo, diff_delta, parent, config);
+        let ignored = file_config.map(|c| c.ignore()).unwrap_or(false);
+        let path = diff_delta
+            .new_file()
+            .path_bytes()
+            .or(diff_delta.old_file().path_bytes())
+            .map(|path| String::from_utf8_lossy(path).into_owned())
+            .unwrap_or_default();
+        let module = config.module_for_path(path.as_bytes());
+        let submodule = submodule_paths.update(diff_delta, &path);
+        // the lines of a submodule are the commit it points to, and those of
+        // ignored files are not classified
+        let blame_path = if submodule.is_some() || ignored {
+            None
+        } else {
+            get_blame_path(diff_delta)
+        };
 
         FileAnalyzer {
             repo,
             commit,
+            parent,
+            config,
             result: AnalyzedDiff::empty(),
             config_context,
-            blame,
+            blame: None,
+            blame_path,
             current_hunk: None,
-            ignored: file_config.map(|c| c.ignore()).unwrap_or(false),
+            ignored: ignored || submodule.is_some(),
+            module,
+            cached_file: CachedFile {
+                path,
+                classified: !ignored,
+                hunks: Vec::new(),
+                submodule: submodule.clone(),
+                binary: None,
+            },
+            submodule,
+        }
+    }
+
+    /// Marks the file as binary, which has no hunks. Binary files which are
+    /// ignored are not counted.
+    pub fn set_binary(&mut self, diff_delta: &DiffDelta) {
+        if self.submodule.is_none() && self.cached_file.classified {
+            self.cached_file.binary = Some(BinaryStats::for_delta(self.repo, diff_delta));
         }
     }
 
     pub fn start_hunk(&mut self) -> Result<(), Error> {
         self.finish_hunk();
+        // blamed lazily, so that files without hunks are never blamed
+        if let Some(blame_path) = self.blame_path.take() {
+            self.blame = get_blame(self.repo, &blame_path, self.parent, self.config);
+        }
         self.current_hunk.replace(HunkAnalyzer::new(
             self.repo,
             self.commit,
@@ -65,15 +117,37 @@ impl<'a> FileAnalyzer<'a> {
     fn finish_hunk(&mut self) {
         if let Some(current_hunk) = self.current_hunk.take() {
             let (blame, hunk_result) = current_hunk.finish();
+            if self.submodule.is_some() {
+                return;
+            }
             self.blame = blame;
+            self.cached_file.hunks.push(WorkStats {
+                impact: 0,
+                ..hunk_result
+            });
             self.result
                 .add_stats(hunk_result, self.config_context.tags());
+            if let Some(ref module) = self.module {
+                self.result.add_module_stats(hunk_result, module);
+            }
         }
     }
 
-    pub fn finish(mut self) -> AnalyzedDiff {
+    /// Returns the analyzed diff along with the raw classification of the
+    /// file.
+    pub fn finish(mut self) -> (AnalyzedDiff, CachedFile) {
         self.finish_hunk();
-        self.result
+        if let Some(submodule) = self.submodule.take() {
+            self.result.add_submodule(submodule);
+        }
+        match self.cached_file.binary {
+            // ignored files are not classified
+            Some(binary) if self.cached_file.classified => {
+                self.result.add_binary(binary, &self.cached_file.path);
+            }
+            _ => {}
+        }
+        (self.result, self.cached_file)
     }
 }
 
@@ -83,33 +157,37 @@ fn get_file_config<'a>(
 ) -> Option<FileConfig<'a>> {
     diff_delta
         .new_file()
-        .path()
-        .or(diff_delta.old_file().path())
-        .and_then(|path| path.to_str().and_then(|path| config.config_for_file(path)))
+        .path_bytes()
+        .or(diff_delta.old_file().path_bytes())
+        .and_then(|path| config.config_for_path(path))
 }
 
 fn get_author_config<'a>(config: &'a WorkingConfig, commit: &Commit) -> Option<&'a ContributorConfig> {
     config.config_for_identity(&commit.author().into())
 }
 
+/// The path of the file to blame the changed lines of, which only modified
+/// files have.
+fn get_blame_path(diff_delta: &DiffDelta) -> Option<PathBuf> {
+    if diff_delta.status() != Delta::Modified {
+        return None;
+    }
+    diff_delta.old_file().path().map(|old_path| old_path.to_owned())
+}
+
 fn get_blame(
     repo: &Repository,
-    diff_delta: &DiffDelta,
+    old_path: &Path,
     parent: Option<&Commit>,
     config: &WorkingConfig,
 ) -> Option<GitBlame> {
-    if diff_delta.status() != Delta::Modified {
-        return None;
-    }
-    diff_delta.old_file().path().and_then(|old_path| {
-        parent.and_then(|parent| {
-            if let Ok(new_blame) =
-                GitBlame::new(&repo, &parent.id(), &old_path, config.churn_cutoff())
-            {
-                Some(new_blame)
-            } else {
-                None
-            }
-        })
+    parent.and_then(|parent| {
+        if let Ok(new_blame) =
+            GitBlame::new(&repo, &parent.id(), old_path, config.churn_cutoff())
+        {
+            Some(new_blame)
+        } else {
+            None
+        }
     })
 }
@@ -95,6 +95,80 @@ impl GitBlame {
     }
 }
 
+/// Blames the lines in each of the inclusive `ranges` of the file at `path`
+/// as of `commit`, returning the distinct commits which last changed them.
+pub fn blame_ranges(
+    repo: &Repository,
+    commit: &Oid,
+    path: &Path,
+    ranges: &[(usize, usize)],
+) -> Result<Vec<Oid>> {
+    let mut oids = Vec::new();
+    for oid in blame_lines(repo, commit, path, ranges)? {
+        if !oids.contains(&oid) {
+            oids.push(oid);
+        }
+    }
+    Ok(oids)
+}
+
+/// Blames every line of the file at `path` as of `commit`, returning the
+/// number of lines last changed by each commit.
+pub fn blame_file(repo: &Repository, commit: &Oid, path: &Path) -> Result<HashMap<Oid, usize>> {
+    let mut counts = HashMap::new();
+    for oid in blame_lines(repo, commit, path, &[])? {
+        *counts.entry(oid).or_insert(0) += 1;
+    }
+    Ok(counts)
+}
+
+/// The commit which last changed each blamed line, in order.
+fn blame_lines(
+    repo: &Repository,
+    commit: &Oid,
+    path: &Path,
+    ranges: &[(usize, usize)],
+) -> Result<Vec<Oid>> {
+    lazy_static! {
+        static ref PORCELAIN_HEADER_REGEX: Regex = Regex::new(r"^([0-9a-f]{40}) \d+ \d+").unwrap();
+    }
+    let mut command = Command::new("git");
+    command
+        .current_dir(repo.path())
+        .arg("blame")
+        .arg("-l")
+        .arg("-s")
+        .arg("--porcelain");
+    for (start, end) in ranges {
+        command.arg("-L").arg(format!("{},{}", start, end));
+    }
+    let output = command
+        .arg(commit.to_string())
+        .arg("--")
+        .arg(path)
+        .output()?;
+    if !output.status.success() {
+        return Err(
+            ErrorKind::BlameError(String::from_utf8_lossy(&output.stderr).into_owned()).into(),
+        );
+    }
+    let mut oids = Vec::new();
+    for line in output.stdout.split(|b| *b == b'\n') {
+        // lines of the blamed file are prefixed with a tab and not valid
+        // UTF-8 necessarily
+        if line.starts_with(b"\t") {
+            continue;
+        }
+        if let Some(captures) = std::str::from_utf8(line)
+            .ok()
+            .and_then(|line| PORCELAIN_HEADER_REGEX.captures(line))
+        {
+            oids.push(Oid::from_str(&captures[1])?);
+        }
+    }
+    Ok(oids)
+}
+
 impl Drop for GitBlame {
     fn drop(&mut self) {
         // need this to prevent zombie "Z+" processes from occuring
@@ -0,0 +1,69 @@
+use chrono::prelude::*;
+use chrono::DateTime;
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use crate::event::Event;
+use crate::repo_info::PartialRepoInfo;
+
+/// Options for `RepoAnalyzer::analyze_hotspots`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HotspotOpts {
+    /// Only changes committed since are counted. All are if not set.
+    pub since: Option<DateTime<Utc>>,
+}
+
+impl Default for HotspotOpts {
+    fn default() -> Self {
+        HotspotOpts { since: None }
+    }
+}
+
+/// A file which is both large and changed often, ranked by the number of
+/// changes times its number of lines.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hotspot {
+    pub id: String,
+    pub path: String,
+
+    pub commit_id: String,
+    pub snapshot_at: DateTime<Utc>,
+    pub since: Option<DateTime<Utc>>,
+
+    /// The number of commits which changed the file.
+    pub changes: usize,
+
+    /// The lines added and deleted by those commits.
+    pub lines_added: usize,
+    pub lines_deleted: usize,
+
+    /// The number of lines of the file as of the snapshot.
+    pub lines: usize,
+
+    pub score: usize,
+
+    /// The position of the file when ranked by score, starting at 1.
+    pub rank: usize,
+
+    pub tags: Vec<String>,
+
+    pub repo: Option<PartialRepoInfo>,
+}
+
+impl Event for Hotspot {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.snapshot_at
+    }
+
+    fn event_type(&self) -> &str {
+        "hotspot"
+    }
+
+    fn id(&self) -> Cow<str> {
+        Cow::Borrowed(&self.id)
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        self.tags.iter().cloned().collect()
+    }
+}
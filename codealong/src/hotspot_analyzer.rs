@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use git2::{Commit, ObjectType, Repository, Sort};
+
+use crate::diff_analyzer::DiffAnalyzer;
+use crate::error::*;
+use crate::hotspot::{Hotspot, HotspotOpts};
+use crate::repo_info::RepoInfo;
+use crate::utils::convert_time;
+use crate::working_config::WorkingConfig;
+
+/// Ranks the files of a commit by how often they changed in its history
+/// times their size.
+pub struct HotspotAnalyzer<'a> {
+    repo: &'a Repository,
+    config: &'a WorkingConfig,
+    repo_info: &'a RepoInfo,
+}
+
+impl<'a> HotspotAnalyzer<'a> {
+    pub fn new(
+        repo: &'a Repository,
+        config: &'a WorkingConfig,
+        repo_info: &'a RepoInfo,
+    ) -> HotspotAnalyzer<'a> {
+        HotspotAnalyzer {
+            repo,
+            config,
+            repo_info,
+        }
+    }
+
+    /// Emits a hotspot for each file at `tip` which changed within the
+    /// window, highest score first. Ignored and binary files are skipped, as
+    /// are merge commits.
+    pub fn analyze(&self, tip: &Commit, opts: &HotspotOpts) -> Result<Vec<Hotspot>> {
+        let mut changes: BTreeMap<String, (usize, usize, usize)> = BTreeMap::new();
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.set_sorting(Sort::TIME);
+        revwalk.push(tip.id())?;
+        for oid in revwalk {
+            let commit = self.repo.find_commit(oid?)?;
+            if let Some(since) = opts.since {
+                if convert_time(&commit.committer().when()) < since {
+                    continue;
+                }
+            }
+            if commit.parent_count() > 1 {
+                continue;
+            }
+            let parent = commit.parents().next();
+            let diff_analyzer = DiffAnalyzer::new(self.repo, &commit, parent.as_ref(), self.config);
+            for (path, additions, deletions) in diff_analyzer.line_stats()? {
+                let (count, added, deleted) = changes.entry(path).or_insert((0, 0, 0));
+                *count += 1;
+                *added += additions;
+                *deleted += deletions;
+            }
+        }
+        let tree = tip.tree()?;
+        let mut result = Vec::new();
+        for (path, (count, lines_added, lines_deleted)) in changes {
+            let file_config = self.config.config_for_file(&path);
+            if file_config.as_ref().map(|c| c.ignore()).unwrap_or(false) {
+                continue;
+            }
+            // files which no longer exist are no hotspots
+            let entry = match tree.get_path(Path::new(&path)) {
+                Ok(entry) => entry,
+                Err(_) => continue,
+            };
+            if entry.kind() != Some(ObjectType::Blob) {
+                continue;
+            }
+            let blob = self.repo.find_blob(entry.id())?;
+            if blob.is_binary() {
+                continue;
+            }
+            let lines = line_count(blob.content());
+            let mut tags: Vec<String> = file_config
+                .map(|c| c.tags().into_iter().map(|t| t.to_owned()).collect())
+                .unwrap_or_default();
+            tags.sort();
+            result.push(Hotspot {
+                id: format!("{}:{}:hotspot:{}", self.repo_info.name, tip.id(), path),
+                path,
+                commit_id: tip.id().to_string(),
+                snapshot_at: convert_time(&tip.committer().when()),
+                since: opts.since,
+                changes: count,
+                lines_added,
+                lines_deleted,
+                lines,
+                score: count * lines,
+                rank: 0,
+                tags,
+                repo: Some(self.repo_info.partial()),
+            });
+        }
+        result.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+        for (index, hotspot) in result.iter_mut().enumerate() {
+            hotspot.rank = index + 1;
+        }
+        Ok(result)
+    }
+}
+
+fn line_count(content: &[u8]) -> usize {
+    let newlines = content.iter().filter(|b| **b == b'\n').count();
+    if content.is_empty() || content.ends_with(b"\n") {
+        newlines
+    } else {
+        newlines + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::prelude::*;
+
+    #[test]
+    fn test_analyze() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/bug_fixes")?;
+        let config = WorkingConfig::default();
+        let repo_info = RepoInfo::default();
+        let analyzer = HotspotAnalyzer::new(&repo, &config, &repo_info);
+        let tip = repo.head()?.peel_to_commit()?;
+        let hotspots = |opts: HotspotOpts| -> Result<Vec<(String, usize, usize, usize, usize)>> {
+            Ok(analyzer
+                .analyze(&tip, &opts)?
+                .into_iter()
+                .map(|h| (h.path, h.changes, h.lines, h.score, h.rank))
+                .collect())
+        };
+        assert_eq!(
+            hotspots(HotspotOpts::default())?,
+            vec![
+                ("calc.txt".to_owned(), 4, 5, 20, 1),
+                ("docs.txt".to_owned(), 1, 1, 1, 2),
+            ]
+        );
+        let opts = HotspotOpts {
+            since: Some(Utc.ymd(2019, 4, 3).and_hms(0, 0, 0)),
+        };
+        assert_eq!(hotspots(opts)?, vec![("calc.txt".to_owned(), 2, 5, 10, 1)]);
+        Ok(())
+    }
+}
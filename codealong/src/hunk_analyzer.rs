@@ -61,7 +61,7 @@ impl<'a> HunkAnalyzer<'a> {
 /// 3. Different languages, file-types, and repositories carry different
 ///    cognitive burdens. This is where Configuration-based weights of work
 ///    stats comes into play. The "weight" field is used as a multiplier.
-fn calculate_impact(work_stats: &WorkStats, weight: f64) -> u64 {
+pub(crate) fn calculate_impact(work_stats: &WorkStats, weight: f64) -> u64 {
     let line_value =
         work_stats.legacy_refactor * 4 + work_stats.help_others * 2 + work_stats.new_work;
     let scaled_line_value = (line_value as f64).powf(0.5);
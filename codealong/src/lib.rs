@@ -16,25 +16,51 @@ extern crate serde_yaml;
 #[macro_use]
 extern crate slog;
 
+mod analysis_cache;
+mod analysis_state;
 mod analyze_opts;
 mod analyzed_commit;
 mod analyzed_diff;
+mod binary_stats;
+mod branches;
+mod bus_factor;
+mod code_owners;
+mod code_owners_cache;
 mod commit_analyzer;
+mod commit_update;
 mod config;
+mod config_cache;
 mod config_context;
 mod contributor;
+mod coupling;
+mod coupling_analyzer;
 mod diff_analyzer;
 mod error;
 mod event;
 mod file_analyzer;
 mod git_blame;
+mod hotspot;
+mod hotspot_analyzer;
 mod hunk_analyzer;
 mod identity;
 mod line_analyzer;
+mod line_survival;
+mod line_survival_analyzer;
+mod ownership;
+mod ownership_analyzer;
+mod parallel_analyzer;
+mod path_matcher;
+mod project;
+mod pull_request_links;
+mod release;
+mod release_analyzer;
 mod repo;
 mod repo_analyzer;
 mod repo_config;
 mod repo_info;
+mod reverts;
+mod submodule_update;
+mod szz;
 pub mod test;
 mod utils;
 mod work_stats;
@@ -42,20 +68,49 @@ mod working_config;
 mod workspace;
 mod workspace_config;
 
+pub use crate::analysis_cache::{AnalysisCache, CachedCommit, CachedFile};
+pub use crate::analysis_state::{AnalysisState, RepoState};
 pub use crate::analyze_opts::AnalyzeOpts;
 pub use crate::analyzed_commit::AnalyzedCommit;
 pub use crate::analyzed_diff::AnalyzedDiff;
+pub use crate::binary_stats::BinaryStats;
+pub use crate::branches::{AnalyzedRef, Branches};
+pub use crate::bus_factor::{BusFactor, BusFactorOpts, Owner};
+pub use crate::code_owners::{ChangeOwners, CodeOwners};
+pub use crate::code_owners_cache::CodeOwnersCache;
 pub use crate::commit_analyzer::CommitAnalyzer;
-pub use crate::config::{Config, ContributorConfig, GlobConfig};
+pub use crate::commit_update::{
+    BugIntroducing, CommitUpdate, Released, Reverted, Reviewed,
+};
+pub use crate::config::{
+    Config, ContributorConfig, GlobConfig, MergeMode, MergeStrategy, WorkingHours,
+};
+pub use crate::config_cache::ConfigCache;
 pub use crate::contributor::Contributor;
+pub use crate::coupling::{Coupling, CouplingOpts};
+pub use crate::coupling_analyzer::CouplingAnalyzer;
 pub use crate::diff_analyzer::DiffAnalyzer;
 pub use crate::error::{Error, ErrorKind};
 pub use crate::event::Event;
+pub use crate::hotspot::{Hotspot, HotspotOpts};
+pub use crate::hotspot_analyzer::HotspotAnalyzer;
 pub use crate::identity::Identity;
+pub use crate::line_survival::{LineSurvival, LineSurvivalOpts};
+pub use crate::line_survival_analyzer::LineSurvivalAnalyzer;
+pub use crate::ownership::{OwnedLines, Ownership};
+pub use crate::ownership_analyzer::OwnershipAnalyzer;
+pub use crate::parallel_analyzer::{ParallelAnalysis, WorkerBudget};
+pub use crate::path_matcher::PathMatcher;
+pub use crate::project::{Project, ProjectConfig};
+pub use crate::pull_request_links::PullRequestLinks;
+pub use crate::release::Release;
+pub use crate::release_analyzer::{ReleaseAnalyzer, ReleaseTag};
 pub use crate::repo::Repo;
 pub use crate::repo_analyzer::{AnalyzedRevwalk, RepoAnalyzer};
 pub use crate::repo_config::RepoConfig;
 pub use crate::repo_info::RepoInfo;
+pub use crate::reverts::Reverts;
+pub use crate::submodule_update::SubmoduleUpdate;
 pub use crate::utils::with_authentication;
 pub use crate::working_config::WorkingConfig;
 pub use crate::workspace::Workspace;
@@ -4,6 +4,10 @@ use crate::error::Error;
 use crate::git_blame::GitBlame;
 use crate::work_stats::WorkStats;
 
+/// Changes to lines last changed less than this many seconds before are
+/// churn or helping others, older ones legacy refactoring.
+pub const CHURN_WINDOW: i64 = 60 * 60 * 24 * 7 * 3;
+
 pub struct LineAnalyzer<'a> {
     repo: &'a Repository,
     commit: &'a Commit<'a>,
@@ -43,7 +47,7 @@ impl<'a> LineAnalyzer<'a> {
 
             let diff_in_seconds = self.commit.committer().when().seconds()
                 - previous_commit.committer().when().seconds();
-            if diff_in_seconds < 60 * 60 * 24 * 7 * 3 {
+            if diff_in_seconds < CHURN_WINDOW {
                 if self.compare_signatures(&previous_commit.author(), &self.commit.author()) {
                     return Ok(WorkStats::churn());
                 } else {
@@ -0,0 +1,114 @@
+use chrono::prelude::*;
+use chrono::{DateTime, Duration};
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use crate::event::Event;
+use crate::repo_info::{PartialRepoInfo, RepoInfo};
+
+/// Options for `RepoAnalyzer::analyze_line_survival`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineSurvivalOpts {
+    /// How long after landing the lines of a commit are checked for.
+    pub horizons: Vec<Duration>,
+
+    /// Commits which landed less than a horizon before this are not measured
+    /// at that horizon.
+    pub as_of: DateTime<Utc>,
+}
+
+impl Default for LineSurvivalOpts {
+    fn default() -> Self {
+        LineSurvivalOpts {
+            horizons: vec![Duration::days(30), Duration::days(90), Duration::days(365)],
+            as_of: Utc::now(),
+        }
+    }
+}
+
+/// How many of the lines added by a contributor, team or to files of a tag
+/// still existed a given time after they landed on the analyzed ref.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LineSurvival {
+    pub id: String,
+
+    /// What the lines are grouped by: `contributor`, `team` or `tag`.
+    pub group_by: String,
+    pub name: String,
+
+    pub horizon_days: i64,
+
+    pub commit_id: String,
+    pub measured_at: DateTime<Utc>,
+
+    pub commit_count: usize,
+
+    /// The lines added, whether new work or rewrites of existing lines.
+    pub introduced: usize,
+    pub surviving: usize,
+    pub survival_rate: f64,
+
+    pub repo: Option<PartialRepoInfo>,
+}
+
+impl LineSurvival {
+    pub(crate) fn new(
+        group_by: &str,
+        name: &str,
+        horizon: Duration,
+        commit_id: &str,
+        measured_at: DateTime<Utc>,
+        repo_info: &RepoInfo,
+    ) -> LineSurvival {
+        LineSurvival {
+            id: format!(
+                "{}:{}:survival:{}:{}:{}",
+                repo_info.name,
+                commit_id,
+                horizon.num_days(),
+                group_by,
+                name
+            ),
+            group_by: group_by.to_owned(),
+            name: name.to_owned(),
+            horizon_days: horizon.num_days(),
+            commit_id: commit_id.to_owned(),
+            measured_at,
+            commit_count: 0,
+            introduced: 0,
+            surviving: 0,
+            survival_rate: 0.0,
+            repo: Some(repo_info.partial()),
+        }
+    }
+
+    pub(crate) fn add(&mut self, introduced: usize, surviving: usize) {
+        self.introduced += introduced;
+        self.surviving += surviving;
+        if self.introduced > 0 {
+            self.survival_rate = self.surviving as f64 / self.introduced as f64;
+        }
+    }
+}
+
+impl Event for LineSurvival {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.measured_at
+    }
+
+    fn event_type(&self) -> &str {
+        "line_survival"
+    }
+
+    fn id(&self) -> Cow<str> {
+        Cow::Borrowed(&self.id)
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        let mut tags = HashSet::new();
+        if self.group_by == "tag" {
+            tags.insert(self.name.clone());
+        }
+        tags
+    }
+}
@@ -0,0 +1,272 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use chrono::prelude::*;
+use chrono::{DateTime, Duration};
+use git2::{Commit, Oid, Repository};
+use slog::Logger;
+
+use crate::contributor::Contributor;
+use crate::diff_analyzer::DiffAnalyzer;
+use crate::error::*;
+use crate::git_blame::blame_file;
+use crate::identity::Identity;
+use crate::line_survival::{LineSurvival, LineSurvivalOpts};
+use crate::repo_info::RepoInfo;
+use crate::utils::convert_time;
+use crate::working_config::WorkingConfig;
+
+/// Measures how many of the lines added by each commit survive, by blaming
+/// the files they were added to a given time after the commit landed.
+pub struct LineSurvivalAnalyzer<'a> {
+    repo: &'a Repository,
+    config: &'a WorkingConfig,
+    repo_info: &'a RepoInfo,
+    logger: Logger,
+}
+
+/// A commit which landed on the analyzed ref along with the lines it added
+/// to each file.
+struct Landed {
+    oid: Oid,
+    landed: usize,
+    author: Contributor,
+    introduced: Vec<(String, usize)>,
+}
+
+impl<'a> LineSurvivalAnalyzer<'a> {
+    pub fn new(
+        repo: &'a Repository,
+        config: &'a WorkingConfig,
+        repo_info: &'a RepoInfo,
+        parent_logger: &Logger,
+    ) -> LineSurvivalAnalyzer<'a> {
+        LineSurvivalAnalyzer {
+            repo,
+            config,
+            repo_info,
+            logger: parent_logger.new(o!()),
+        }
+    }
+
+    /// Emits the survival of the lines added in the history of `tip` at each
+    /// horizon, per contributor, team and tag. Commits land when they become
+    /// reachable from the first-parent history of `tip`, and their lines are
+    /// blamed as of the last commit of that history on the day the horizon
+    /// ends. Commits measured on the same day share a snapshot, in which each
+    /// file is blamed once.
+    pub fn analyze(&self, tip: &Commit, opts: &LineSurvivalOpts) -> Result<Vec<LineSurvival>> {
+        let first_parents = self.first_parents(tip)?;
+        let landed = self.landed(&first_parents)?;
+        let mut snapshots: BTreeMap<usize, Vec<(Duration, &Landed)>> = BTreeMap::new();
+        for horizon in &opts.horizons {
+            for commit in &landed {
+                let deadline = first_parents[commit.landed].1 + *horizon;
+                if deadline > opts.as_of {
+                    continue;
+                }
+                let end_of_day = std::cmp::min(deadline.date().succ().and_hms(0, 0, 0), opts.as_of);
+                let mut snapshot = commit.landed;
+                while snapshot + 1 < first_parents.len()
+                    && first_parents[snapshot + 1].1 < end_of_day
+                {
+                    snapshot += 1;
+                }
+                snapshots
+                    .entry(snapshot)
+                    .or_insert_with(Vec::new)
+                    .push((*horizon, commit));
+            }
+        }
+        let commit_id = tip.id().to_string();
+        let measured_at = convert_time(&tip.committer().when());
+        let mut survivals: BTreeMap<(i64, String, String), LineSurvival> = BTreeMap::new();
+        let mut commits: HashSet<(i64, String, String, Oid)> = HashSet::new();
+        for (snapshot, measured) in snapshots {
+            let mut blames: HashMap<&str, HashMap<Oid, usize>> = HashMap::new();
+            for (horizon, commit) in measured {
+                for (path, introduced) in &commit.introduced {
+                    if !blames.contains_key(path.as_str()) {
+                        let counts = self.blame(first_parents[snapshot].0, path)?;
+                        blames.insert(path, counts);
+                    }
+                    let surviving = blames[path.as_str()].get(&commit.oid).cloned().unwrap_or(0);
+                    let mut groups = vec![("contributor", commit.author.id.clone())];
+                    groups.extend(commit.author.teams.iter().map(|t| ("team", t.clone())));
+                    if let Some(file_config) = self.config.config_for_file(path) {
+                        groups.extend(
+                            file_config
+                                .tags()
+                                .into_iter()
+                                .map(|t| ("tag", t.to_owned())),
+                        );
+                    }
+                    for (group_by, name) in groups {
+                        let key = (horizon.num_days(), group_by.to_owned(), name);
+                        let survival = survivals.entry(key.clone()).or_insert_with(|| {
+                            LineSurvival::new(
+                                group_by,
+                                &key.2,
+                                horizon,
+                                &commit_id,
+                                measured_at,
+                                self.repo_info,
+                            )
+                        });
+                        survival.add(*introduced, surviving);
+                        if commits.insert((key.0, key.1, key.2, commit.oid)) {
+                            survival.commit_count += 1;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(survivals
+            .into_iter()
+            .map(|(_, survival)| survival)
+            .collect())
+    }
+
+    /// The first-parent history of `tip` along with when each commit was
+    /// committed, oldest first.
+    fn first_parents(&self, tip: &Commit) -> Result<Vec<(Oid, DateTime<Utc>)>> {
+        let mut result = Vec::new();
+        let mut commit = tip.clone();
+        loop {
+            result.push((commit.id(), convert_time(&commit.committer().when())));
+            if commit.parent_count() == 0 {
+                break;
+            }
+            commit = commit.parent(0)?;
+        }
+        result.reverse();
+        Ok(result)
+    }
+
+    /// The commits which landed with each of `first_parents`, i.e. the
+    /// commit itself and those it merged. Merge commits are skipped, as the
+    /// lines they add are the ones of the commits they merge.
+    fn landed(&self, first_parents: &[(Oid, DateTime<Utc>)]) -> Result<Vec<Landed>> {
+        let mut result = Vec::new();
+        for (index, (oid, _)) in first_parents.iter().enumerate() {
+            let commit = self.repo.find_commit(*oid)?;
+            let mut revwalk = self.repo.revwalk()?;
+            revwalk.push(commit.id())?;
+            if commit.parent_count() > 0 {
+                revwalk.hide(commit.parent_id(0)?)?;
+            }
+            for landed_oid in revwalk {
+                let landed = self.repo.find_commit(landed_oid?)?;
+                if landed.parent_count() > 1 {
+                    continue;
+                }
+                let introduced = self.introduced(&landed)?;
+                if introduced.is_empty() {
+                    continue;
+                }
+                result.push(Landed {
+                    oid: landed.id(),
+                    landed: index,
+                    author: self
+                        .config
+                        .contributor_for_identity(&Identity::from(landed.author())),
+                    introduced,
+                });
+            }
+        }
+        Ok(result)
+    }
+
+    /// The new work of `commit` in each file which is not ignored, i.e. the
+    /// number of lines it added there.
+    fn introduced(&self, commit: &Commit) -> Result<Vec<(String, usize)>> {
+        let parent = commit.parents().next();
+        let diff_analyzer = DiffAnalyzer::new(self.repo, commit, parent.as_ref(), self.config);
+        Ok(diff_analyzer
+            .line_stats()?
+            .into_iter()
+            .filter(|(path, additions, _)| {
+                *additions > 0
+                    && !self
+                        .config
+                        .config_for_file(path)
+                        .map(|c| c.ignore())
+                        .unwrap_or(false)
+            })
+            .map(|(path, additions, _)| (path, additions))
+            .collect())
+    }
+
+    /// The number of lines of the file at `path` as of `oid` last changed by
+    /// each commit, or nothing if it no longer exists.
+    fn blame(&self, oid: Oid, path: &str) -> Result<HashMap<Oid, usize>> {
+        if self
+            .repo
+            .find_commit(oid)?
+            .tree()?
+            .get_path(Path::new(path))
+            .is_err()
+        {
+            return Ok(HashMap::new());
+        }
+        match blame_file(self.repo, &oid, Path::new(path)) {
+            Ok(counts) => Ok(counts),
+            Err(e) => {
+                let error = e.to_string();
+                warn!(self.logger, "Could not blame {}", path; "error" => error);
+                Ok(HashMap::new())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::build_test_logger;
+
+    #[test]
+    fn test_analyze() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/bug_fixes")?;
+        let config = WorkingConfig::default();
+        let repo_info = RepoInfo::default();
+        let analyzer = LineSurvivalAnalyzer::new(&repo, &config, &repo_info, &build_test_logger());
+        let tip = repo.head()?.peel_to_commit()?;
+        let survival = |opts: LineSurvivalOpts| -> Result<Vec<(i64, String, usize, usize)>> {
+            Ok(analyzer
+                .analyze(&tip, &opts)?
+                .into_iter()
+                .map(|s| (s.horizon_days, s.name, s.introduced, s.surviving))
+                .collect())
+        };
+        let alice = "Alice <alice@example.com>".to_owned();
+        let bob = "Bob <bob@example.com>".to_owned();
+
+        // After a day, the "two" added by Alice had been changed to "2" by
+        // Bob. After three days, Bob had changed it back.
+        let opts = LineSurvivalOpts {
+            horizons: vec![Duration::days(1), Duration::days(3)],
+            as_of: Utc.ymd(2019, 6, 1).and_hms(0, 0, 0),
+        };
+        assert_eq!(
+            survival(opts)?,
+            vec![
+                (1, alice.clone(), 4, 3),
+                (1, bob.clone(), 4, 4),
+                (3, alice.clone(), 4, 3),
+                (3, bob.clone(), 4, 3),
+            ]
+        );
+
+        // the bug fix landed less than a day before
+        let opts = LineSurvivalOpts {
+            horizons: vec![Duration::days(1)],
+            as_of: Utc.ymd(2019, 4, 4).and_hms(23, 0, 0),
+        };
+        assert_eq!(
+            survival(opts)?,
+            vec![(1, alice.clone(), 4, 3), (1, bob.clone(), 2, 2)]
+        );
+        Ok(())
+    }
+}
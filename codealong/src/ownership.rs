@@ -0,0 +1,117 @@
+use chrono::prelude::*;
+use chrono::DateTime;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+use crate::contributor::Contributor;
+use crate::event::Event;
+use crate::repo_info::{PartialRepoInfo, RepoInfo};
+
+/// A snapshot of who owns the lines of a file or directory as of a commit,
+/// according to blame.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ownership {
+    pub id: String,
+
+    /// The path of the file or directory. Empty for the repository root.
+    pub path: String,
+    pub directory: bool,
+
+    pub commit_id: String,
+    pub snapshot_at: DateTime<Utc>,
+
+    pub lines: usize,
+
+    /// The lines owned by each contributor, most lines first.
+    pub contributors: Vec<OwnedLines>,
+
+    /// The lines owned by each team, most lines first. Lines of contributors
+    /// without a team are not included.
+    pub teams: Vec<OwnedLines>,
+
+    pub repo: Option<PartialRepoInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OwnedLines {
+    pub id: String,
+    pub lines: usize,
+}
+
+/// Counts the lines owned by each contributor and team.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Tally {
+    lines: usize,
+    contributors: HashMap<String, usize>,
+    teams: HashMap<String, usize>,
+}
+
+impl Tally {
+    pub fn add(&mut self, contributor: &Contributor, lines: usize) {
+        self.lines += lines;
+        *self.contributors.entry(contributor.id.clone()).or_insert(0) += lines;
+        for team in &contributor.teams {
+            *self.teams.entry(team.clone()).or_insert(0) += lines;
+        }
+    }
+
+    pub fn merge(&mut self, other: &Tally) {
+        self.lines += other.lines;
+        for (id, lines) in &other.contributors {
+            *self.contributors.entry(id.clone()).or_insert(0) += lines;
+        }
+        for (team, lines) in &other.teams {
+            *self.teams.entry(team.clone()).or_insert(0) += lines;
+        }
+    }
+}
+
+impl Ownership {
+    pub(crate) fn new(
+        path: &str,
+        directory: bool,
+        commit_id: &str,
+        snapshot_at: DateTime<Utc>,
+        tally: Tally,
+        repo_info: &RepoInfo,
+    ) -> Ownership {
+        Ownership {
+            id: format!("{}:{}:{}", repo_info.name, commit_id, path),
+            path: path.to_owned(),
+            directory,
+            commit_id: commit_id.to_owned(),
+            snapshot_at,
+            lines: tally.lines,
+            contributors: sorted(tally.contributors),
+            teams: sorted(tally.teams),
+            repo: Some(repo_info.partial()),
+        }
+    }
+}
+
+fn sorted(lines: HashMap<String, usize>) -> Vec<OwnedLines> {
+    let mut result: Vec<OwnedLines> = lines
+        .into_iter()
+        .map(|(id, lines)| OwnedLines { id, lines })
+        .collect();
+    result.sort_by(|a, b| b.lines.cmp(&a.lines).then_with(|| a.id.cmp(&b.id)));
+    result
+}
+
+impl Event for Ownership {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.snapshot_at
+    }
+
+    fn event_type(&self) -> &str {
+        "ownership"
+    }
+
+    fn id(&self) -> Cow<str> {
+        Cow::Borrowed(&self.id)
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        HashSet::new()
+    }
+}
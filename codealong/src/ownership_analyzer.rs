@@ -0,0 +1,213 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use chrono::prelude::*;
+use chrono::DateTime;
+use git2::{Commit, ObjectType, Oid, Repository, TreeWalkMode, TreeWalkResult};
+use slog::Logger;
+
+use crate::contributor::Contributor;
+use crate::error::*;
+use crate::git_blame::blame_file;
+use crate::identity::Identity;
+use crate::ownership::{Ownership, Tally};
+use crate::repo_info::RepoInfo;
+use crate::utils::convert_time;
+use crate::working_config::WorkingConfig;
+
+/// Blames every file of a commit to find who owns which lines.
+pub struct OwnershipAnalyzer<'a> {
+    repo: &'a Repository,
+    config: &'a WorkingConfig,
+    repo_info: &'a RepoInfo,
+    logger: Logger,
+}
+
+impl<'a> OwnershipAnalyzer<'a> {
+    pub fn new(
+        repo: &'a Repository,
+        config: &'a WorkingConfig,
+        repo_info: &'a RepoInfo,
+        parent_logger: &Logger,
+    ) -> OwnershipAnalyzer<'a> {
+        OwnershipAnalyzer {
+            repo,
+            config,
+            repo_info,
+            logger: parent_logger.new(o!()),
+        }
+    }
+
+    /// Emits the ownership of each file at `commit` which is neither ignored
+    /// nor binary, followed by that of each directory containing them,
+    /// including the repository root.
+    pub fn analyze(&self, commit: &Commit) -> Result<Vec<Ownership>> {
+        let commit_id = commit.id().to_string();
+        let snapshot_at = convert_time(&commit.committer().when());
+        let mut files: BTreeMap<String, Tally> = BTreeMap::new();
+        let mut directories: BTreeMap<String, Tally> = BTreeMap::new();
+        let mut authors: HashMap<Oid, Contributor> = HashMap::new();
+        for path in self.files(commit)? {
+            let counts = match blame_file(self.repo, &commit.id(), Path::new(&path)) {
+                Ok(counts) => counts,
+                Err(e) => {
+                    let error = e.to_string();
+                    warn!(self.logger, "Could not blame {}", path; "error" => error);
+                    continue;
+                }
+            };
+            let mut tally = Tally::default();
+            for (oid, lines) in counts {
+                if !authors.contains_key(&oid) {
+                    let identity = Identity::from(self.repo.find_commit(oid)?.author());
+                    authors.insert(oid, self.config.contributor_for_identity(&identity));
+                }
+                tally.add(&authors[&oid], lines);
+            }
+            for directory in parent_directories(&path) {
+                directories
+                    .entry(directory.to_owned())
+                    .or_insert_with(Tally::default)
+                    .merge(&tally);
+            }
+            files.insert(path, tally);
+        }
+        let files = files.into_iter().map(|(path, tally)| (path, false, tally));
+        let directories = directories
+            .into_iter()
+            .map(|(path, tally)| (path, true, tally));
+        Ok(files
+            .chain(directories)
+            .map(|(path, directory, tally)| {
+                Ownership::new(
+                    &path,
+                    directory,
+                    &commit_id,
+                    snapshot_at,
+                    tally,
+                    self.repo_info,
+                )
+            })
+            .collect())
+    }
+
+    /// When each contributor last authored a commit in the history of any of
+    /// `tips`, e.g. the analyzed refs, so that work on other branches counts
+    /// as activity.
+    pub fn last_active_at(&self, tips: &[Oid]) -> Result<HashMap<String, DateTime<Utc>>> {
+        let mut result: HashMap<String, DateTime<Utc>> = HashMap::new();
+        let mut revwalk = self.repo.revwalk()?;
+        for oid in tips {
+            revwalk.push(*oid)?;
+        }
+        for oid in revwalk {
+            let commit = self.repo.find_commit(oid?)?;
+            let authored_at = convert_time(&commit.author().when());
+            let identity = Identity::from(commit.author());
+            let contributor = self.config.contributor_for_identity(&identity);
+            let last_active_at = result.entry(contributor.id).or_insert(authored_at);
+            *last_active_at = (*last_active_at).max(authored_at);
+        }
+        Ok(result)
+    }
+
+    /// The paths of the files at `commit` which are neither ignored nor
+    /// binary.
+    fn files(&self, commit: &Commit) -> Result<Vec<String>> {
+        let mut blobs = Vec::new();
+        commit.tree()?.walk(TreeWalkMode::PreOrder, |root, entry| {
+            // paths which are not valid UTF-8 are skipped, as are submodules
+            if let (Some(ObjectType::Blob), Some(name)) = (entry.kind(), entry.name()) {
+                blobs.push((format!("{}{}", root, name), entry.id()));
+            }
+            TreeWalkResult::Ok
+        })?;
+        let mut paths = Vec::new();
+        for (path, oid) in blobs {
+            let ignored = self
+                .config
+                .config_for_file(&path)
+                .map(|c| c.ignore())
+                .unwrap_or(false);
+            if ignored || self.repo.find_blob(oid)?.is_binary() {
+                continue;
+            }
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+}
+
+/// The directories containing `path`, starting with the repository root.
+fn parent_directories(path: &str) -> impl Iterator<Item = &str> {
+    std::iter::once("").chain(
+        path.match_indices('/')
+            .map(move |(index, _)| &path[..index]),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::build_test_logger;
+
+    #[test]
+    fn test_parent_directories() {
+        assert_eq!(
+            parent_directories("src/bin/main.rs").collect::<Vec<_>>(),
+            vec!["", "src", "src/bin"]
+        );
+        assert_eq!(
+            parent_directories("README.md").collect::<Vec<_>>(),
+            vec![""]
+        );
+    }
+
+    #[test]
+    fn test_analyze() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/bug_fixes")?;
+        let config = WorkingConfig::default();
+        let repo_info = RepoInfo::default();
+        let analyzer = OwnershipAnalyzer::new(&repo, &config, &repo_info, &build_test_logger());
+        let commit = repo.head()?.peel_to_commit()?;
+        let ownerships = analyzer.analyze(&commit)?;
+        let paths: Vec<(&str, bool)> = ownerships
+            .iter()
+            .map(|o| (o.path.as_str(), o.directory))
+            .collect();
+        assert_eq!(
+            paths,
+            vec![("calc.txt", false), ("docs.txt", false), ("", true)]
+        );
+
+        // calc.txt: "one", "three" and "four" by Alice, "two" and "five" by
+        // Bob
+        let calc = &ownerships[0];
+        assert_eq!(calc.lines, 5);
+        assert_eq!(calc.contributors[0].id, "Alice <alice@example.com>");
+        assert_eq!(calc.contributors[0].lines, 3);
+        assert_eq!(calc.contributors[1].lines, 2);
+
+        let root = &ownerships[2];
+        assert_eq!(root.lines, 6);
+        assert_eq!(root.contributors[0].lines, 3);
+        assert_eq!(root.contributors[1].lines, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_last_active_at() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/bug_fixes")?;
+        let config = WorkingConfig::default();
+        let repo_info = RepoInfo::default();
+        let analyzer = OwnershipAnalyzer::new(&repo, &config, &repo_info, &build_test_logger());
+        let head = repo.head()?.peel_to_commit()?;
+        let parent = head.parent(0)?;
+        let bob = "Bob <bob@example.com>";
+        let last_active_at = analyzer.last_active_at(&[parent.id()])?;
+        assert_eq!(last_active_at[bob], Utc.ymd(2019, 4, 2).and_hms(12, 0, 0));
+        let last_active_at = analyzer.last_active_at(&[parent.id(), head.id()])?;
+        assert_eq!(last_active_at[bob], Utc.ymd(2019, 4, 4).and_hms(12, 0, 0));
+        Ok(())
+    }
+}
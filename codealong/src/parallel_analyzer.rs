@@ -0,0 +1,331 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use chrono::{DateTime, Utc};
+use git2::{Oid, Repository};
+use slog::Logger;
+
+use crate::analysis_cache::AnalysisCache;
+use crate::analyzed_commit::AnalyzedCommit;
+use crate::code_owners_cache::CodeOwnersCache;
+use crate::commit_analyzer::CommitAnalyzer;
+use crate::error::*;
+use crate::project::Project;
+use crate::repo_info::RepoInfo;
+use crate::working_config::WorkingConfig;
+
+/// A number of worker threads which can be shared between several analyses,
+/// e.g. between all of the repositories analyzed by a single run.
+pub struct WorkerBudget {
+    available: Mutex<usize>,
+}
+
+impl WorkerBudget {
+    pub fn new(available: usize) -> WorkerBudget {
+        WorkerBudget {
+            available: Mutex::new(available),
+        }
+    }
+
+    /// Takes a worker from the budget if one is available.
+    pub fn try_acquire(&self) -> bool {
+        let mut available = self.available.lock().unwrap();
+        if *available > 0 {
+            *available -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns a worker to the budget.
+    pub fn release(&self) {
+        *self.available.lock().unwrap() += 1;
+    }
+}
+
+struct Job {
+    index: usize,
+    oid: Oid,
+    config: Arc<WorkingConfig>,
+    cache: Option<Arc<AnalysisCache>>,
+    code_owners_cache: Arc<CodeOwnersCache>,
+    blame_bug_fixes: bool,
+    branches: Vec<String>,
+    on_default_branch: bool,
+    released_at: Option<DateTime<Utc>>,
+    pull_request_number: Option<u64>,
+    reverts: (Option<Oid>, Option<Oid>),
+    submodule_repos: Arc<BTreeMap<String, PathBuf>>,
+    projects: Arc<Vec<Project>>,
+}
+
+/// How many commits may be analyzed ahead of the next one to be yielded. This
+/// bounds the results buffered while a slow commit holds up the others.
+const WINDOW: usize = 256;
+
+/// The commits left to analyze, handed out to the workers in order but no
+/// further than `WINDOW` ahead of the next commit to be yielded.
+struct JobQueue {
+    state: Mutex<JobQueueState>,
+    advanced: Condvar,
+}
+
+struct JobQueueState {
+    jobs: VecDeque<Job>,
+    next_index: usize,
+}
+
+impl JobQueue {
+    fn new(jobs: VecDeque<Job>) -> JobQueue {
+        JobQueue {
+            state: Mutex::new(JobQueueState {
+                jobs,
+                next_index: 0,
+            }),
+            advanced: Condvar::new(),
+        }
+    }
+
+    /// Takes the next job, waiting for it to fall within the window. Returns
+    /// `None` once there are no jobs left.
+    fn pop(&self) -> Option<Job> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            let within_window = match state.jobs.front() {
+                Some(job) => job.index < state.next_index + WINDOW,
+                None => return None,
+            };
+            if within_window {
+                return state.jobs.pop_front();
+            }
+            state = self.advanced.wait(state).unwrap();
+        }
+    }
+
+    /// Moves the window along once the commit before `next_index` has been
+    /// yielded.
+    fn advance(&self, next_index: usize) {
+        self.state.lock().unwrap().next_index = next_index;
+        self.advanced.notify_all();
+    }
+
+    fn is_empty(&self) -> bool {
+        self.state.lock().unwrap().jobs.is_empty()
+    }
+
+    /// Removes all remaining jobs, waking up any waiting workers.
+    fn drain(&self) -> Vec<Job> {
+        let jobs = self.state.lock().unwrap().jobs.drain(..).collect();
+        self.advanced.notify_all();
+        jobs
+    }
+}
+
+/// Analyzes commits on a pool of worker threads, each with its own
+/// `git2::Repository` handle, and yields the results in revwalk order.
+///
+/// One worker is always running. Additional workers are started whenever
+/// the budget allows, and are returned to it once there are no more commits
+/// left to pick up.
+pub struct ParallelAnalysis {
+    path: PathBuf,
+    repo_info: Arc<RepoInfo>,
+    logger: Logger,
+    budget: Arc<WorkerBudget>,
+    jobs: Arc<JobQueue>,
+    len: usize,
+    next_index: usize,
+    pending: BTreeMap<usize, Result<Vec<AnalyzedCommit>>>,
+    /// The remaining parts of the last commit split by project.
+    parts: VecDeque<AnalyzedCommit>,
+    split: bool,
+    sender: Option<Sender<(usize, Result<Vec<AnalyzedCommit>>)>>,
+    receiver: Receiver<(usize, Result<Vec<AnalyzedCommit>>)>,
+}
+
+impl ParallelAnalysis {
+    pub(crate) fn new<'repo, I>(
+        repo: &Repository,
+        commit_analyzers: I,
+        repo_info: &RepoInfo,
+        budget: Arc<WorkerBudget>,
+        logger: &Logger,
+    ) -> Result<ParallelAnalysis>
+    where
+        I: Iterator<Item = Result<CommitAnalyzer<'repo>>>,
+    {
+        let mut jobs = VecDeque::new();
+        let mut split = false;
+        for (index, commit_analyzer) in commit_analyzers.enumerate() {
+            let commit_analyzer = commit_analyzer?;
+            let (branches, on_default_branch) = commit_analyzer.branches();
+            split |= !commit_analyzer.projects().is_empty();
+            jobs.push_back(Job {
+                index,
+                oid: commit_analyzer.commit().id(),
+                config: commit_analyzer.config().clone(),
+                cache: commit_analyzer.cache().cloned(),
+                code_owners_cache: commit_analyzer.code_owners_cache().clone(),
+                blame_bug_fixes: commit_analyzer.blame_bug_fixes(),
+                branches: branches.to_vec(),
+                on_default_branch,
+                released_at: commit_analyzer.released_at(),
+                pull_request_number: commit_analyzer.pull_request_number(),
+                reverts: commit_analyzer.reverts(),
+                submodule_repos: commit_analyzer.submodule_repos().clone(),
+                projects: commit_analyzer.projects().clone(),
+            });
+        }
+        let (sender, receiver) = channel();
+        let mut analysis = ParallelAnalysis {
+            path: repo.path().to_owned(),
+            repo_info: Arc::new(repo_info.clone()),
+            logger: logger.clone(),
+            budget,
+            len: jobs.len(),
+            jobs: Arc::new(JobQueue::new(jobs)),
+            next_index: 0,
+            pending: BTreeMap::new(),
+            parts: VecDeque::new(),
+            split,
+            sender: Some(sender),
+            receiver,
+        };
+        analysis.spawn_worker(false);
+        Ok(analysis)
+    }
+
+    /// Starts workers for as long as the budget allows and commits remain.
+    fn spawn_workers(&mut self) {
+        if self.jobs.is_empty() {
+            // No more workers will be needed. Dropping the sender lets the
+            // receiver notice if every worker has exited.
+            self.sender = None;
+            return;
+        }
+        while self.budget.try_acquire() {
+            self.spawn_worker(true);
+        }
+    }
+
+    fn spawn_worker(&self, from_budget: bool) {
+        let sender = match self.sender {
+            Some(ref sender) => sender.clone(),
+            None => return,
+        };
+        let path = self.path.clone();
+        let repo_info = self.repo_info.clone();
+        let logger = self.logger.clone();
+        let budget = self.budget.clone();
+        let jobs = self.jobs.clone();
+        thread::spawn(move || {
+            match Repository::open(&path) {
+                Ok(repo) => loop {
+                    let job = match jobs.pop() {
+                        Some(job) => job,
+                        None => break,
+                    };
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                        analyze_commit(&repo, &job, &repo_info, &logger)
+                    }))
+                    .unwrap_or_else(|_| Err(ErrorKind::WorkerPanicked(job.oid.to_string()).into()));
+                    if sender.send((job.index, result)).is_err() {
+                        break;
+                    }
+                },
+                Err(e) => {
+                    // Fail the remaining commits so that the analysis does
+                    // not wait on them forever.
+                    for job in jobs.drain() {
+                        let message = format!("could not open repository: {}", e);
+                        let _ = sender.send((job.index, Err(message.into())));
+                    }
+                }
+            }
+            if from_budget {
+                budget.release();
+            }
+        });
+    }
+}
+
+fn analyze_commit(
+    repo: &Repository,
+    job: &Job,
+    repo_info: &RepoInfo,
+    logger: &Logger,
+) -> Result<Vec<AnalyzedCommit>> {
+    let commit = repo.find_commit(job.oid)?;
+    let mut commit_analyzer =
+        CommitAnalyzer::new(repo, commit, job.config.clone(), repo_info, logger);
+    if let Some(ref cache) = job.cache {
+        commit_analyzer.set_cache(cache.clone());
+    }
+    commit_analyzer.set_code_owners_cache(job.code_owners_cache.clone());
+    commit_analyzer.set_blame_bug_fixes(job.blame_bug_fixes);
+    commit_analyzer.set_branches(job.branches.clone(), job.on_default_branch);
+    commit_analyzer.set_released_at(job.released_at);
+    commit_analyzer.set_pull_request_number(job.pull_request_number);
+    commit_analyzer.set_reverts(job.reverts.0, job.reverts.1);
+    commit_analyzer.set_submodule_repos(job.submodule_repos.clone());
+    commit_analyzer.set_projects(job.projects.clone());
+    commit_analyzer.analyze_projects()
+}
+
+impl Iterator for ParallelAnalysis {
+    type Item = Result<AnalyzedCommit>;
+
+    fn next(&mut self) -> Option<Result<AnalyzedCommit>> {
+        if let Some(part) = self.parts.pop_front() {
+            return Some(Ok(part));
+        }
+        if self.next_index >= self.len {
+            return None;
+        }
+        self.spawn_workers();
+        loop {
+            if let Some(result) = self.pending.remove(&self.next_index) {
+                self.next_index += 1;
+                self.jobs.advance(self.next_index);
+                match result {
+                    Ok(parts) => {
+                        self.parts.extend(parts);
+                        match self.parts.pop_front() {
+                            Some(part) => return Some(Ok(part)),
+                            None => return self.next(),
+                        }
+                    }
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+            match self.receiver.recv() {
+                Ok((index, result)) => {
+                    self.pending.insert(index, result);
+                }
+                Err(_) => {
+                    self.next_index = self.len;
+                    return Some(Err("analysis workers exited unexpectedly".into()));
+                }
+            }
+        }
+    }
+
+    /// Commits split by project yield more than one result, so the upper
+    /// bound is only known for repos without projects.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.parts.len() + self.len - self.next_index;
+        (remaining, if self.split { None } else { Some(remaining) })
+    }
+}
+
+impl Drop for ParallelAnalysis {
+    fn drop(&mut self) {
+        // Stop workers from picking up further commits.
+        self.jobs.drain();
+    }
+}
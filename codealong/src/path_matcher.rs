@@ -0,0 +1,233 @@
+use regex::bytes::{Regex, RegexSet};
+
+/// A set of path patterns which is compiled once and then matched against
+/// many paths in a single pass.
+///
+/// Each pattern is one of:
+///
+/// * a glob, e.g. `spec/**/*_spec.rb`, with the same semantics as
+///   `glob::Pattern` where `*` may also match path separators
+/// * a regular expression prefixed with `regex:`, e.g.
+///   `regex:^db/migrate/\d+_.*\.rb$`
+///
+/// Either kind can be prefixed with `!` to negate it, in which case it matches
+/// every path the underlying pattern does not match.
+///
+/// Paths are matched as bytes, so paths which are not valid UTF-8 are
+/// supported. Patterns which fail to compile never match.
+pub struct PathMatcher {
+    set: RegexSet,
+    /// For each pattern, its index within `set` (if it compiled) and whether
+    /// it is negated.
+    patterns: Vec<(Option<usize>, bool)>,
+}
+
+impl PathMatcher {
+    /// Compiles the `(pattern, case_insensitive)` pairs into a matcher.
+    pub fn new<'a, I>(patterns: I) -> PathMatcher
+    where
+        I: IntoIterator<Item = (&'a str, bool)>,
+    {
+        let mut regexes = Vec::new();
+        let mut negations = Vec::new();
+        for (pattern, case_insensitive) in patterns {
+            let (negated, pattern) = if pattern.starts_with('!') {
+                (true, &pattern[1..])
+            } else {
+                (false, pattern)
+            };
+            let regex = if pattern.starts_with("regex:") {
+                Some(pattern["regex:".len()..].to_owned())
+            } else {
+                glob_to_regex(pattern)
+            };
+            regexes.push(regex.map(|regex| {
+                if case_insensitive {
+                    format!("(?i){}", regex)
+                } else {
+                    regex
+                }
+            }));
+            negations.push(negated);
+        }
+        let (set, indices) = compile_regex_set(regexes);
+        PathMatcher {
+            set,
+            patterns: indices.into_iter().zip(negations).collect(),
+        }
+    }
+
+    /// Returns, for each pattern in the order given to `new`, whether it
+    /// matches `path`.
+    pub fn matches(&self, path: &[u8]) -> Vec<bool> {
+        let set_matches = self.set.matches(path);
+        self.patterns
+            .iter()
+            .map(|&(index, negated)| match index {
+                Some(index) => set_matches.matched(index) != negated,
+                None => false,
+            })
+            .collect()
+    }
+}
+
+/// Compiles `regexes` into a set, leaving out those which are missing or fail
+/// to compile. Returns the set along with the index of each regex within it.
+pub fn compile_regex_set<I>(regexes: I) -> (RegexSet, Vec<Option<usize>>)
+where
+    I: IntoIterator<Item = Option<String>>,
+{
+    let mut valid = Vec::new();
+    let indices = regexes
+        .into_iter()
+        .map(|regex| {
+            let regex = regex.filter(|regex| Regex::new(regex).is_ok())?;
+            valid.push(regex);
+            Some(valid.len() - 1)
+        })
+        .collect();
+    let set = RegexSet::new(&valid).expect("regexes were validated individually");
+    (set, indices)
+}
+
+/// Translates a glob into an anchored regular expression. Returns `None` if
+/// the glob is invalid.
+fn glob_to_regex(glob: &str) -> Option<String> {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut res = String::from("(?s-u)^");
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '?' => {
+                res.push('.');
+                i += 1;
+            }
+            '*' => {
+                let start = i;
+                while i < chars.len() && chars[i] == '*' {
+                    i += 1;
+                }
+                match i - start {
+                    1 => res.push_str(".*"),
+                    2 => {
+                        // `**` is only valid as an entire path component
+                        if start > 0 && chars[start - 1] != '/' {
+                            return None;
+                        }
+                        if i == chars.len() {
+                            res.push_str(".*");
+                        } else if chars[i] == '/' {
+                            res.push_str("(?:.*/)?");
+                            i += 1;
+                        } else {
+                            return None;
+                        }
+                    }
+                    _ => return None,
+                }
+            }
+            '[' => {
+                let negated = chars.get(i + 1) == Some(&'!');
+                let start = if negated { i + 2 } else { i + 1 };
+                // the first character of a class is always literal, even `]`
+                let end = chars
+                    .iter()
+                    .skip(start + 1)
+                    .position(|c| *c == ']')
+                    .map(|j| start + 1 + j)?;
+                res.push('[');
+                if negated {
+                    res.push('^');
+                }
+                let class = &chars[start..end];
+                let mut j = 0;
+                while j < class.len() {
+                    if j + 2 < class.len() && class[j + 1] == '-' {
+                        push_class_char(&mut res, class[j]);
+                        res.push('-');
+                        push_class_char(&mut res, class[j + 2]);
+                        j += 3;
+                    } else {
+                        push_class_char(&mut res, class[j]);
+                        j += 1;
+                    }
+                }
+                res.push(']');
+                i = end + 1;
+            }
+            c => {
+                res.push_str(&regex::escape(&c.to_string()));
+                i += 1;
+            }
+        }
+    }
+    res.push('$');
+    Some(res)
+}
+
+/// Pushes `c` into a character class, escaping it only if it is a meta
+/// character, as escaping any other character is an error.
+fn push_class_char(res: &mut String, c: char) {
+    res.push_str(&regex::escape(&c.to_string()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        PathMatcher::new(vec![(pattern, false)]).matches(path.as_bytes())[0]
+    }
+
+    #[test]
+    fn test_globs() {
+        assert!(matches("**/*.rb", "schema.rb"));
+        assert!(matches("**/*.rb", "db/schema.rb"));
+        assert!(matches("*.md", "docs/README.md"));
+        assert!(matches("spec/**/*_spec.rb", "spec/models/code_spec.rb"));
+        assert!(matches("config/**/*", "config/initializers/app.rb"));
+        assert!(matches("Gemfile*", "Gemfile.lock"));
+        assert!(matches("file[0-9].txt", "file1.txt"));
+        assert!(matches("file[!0-9].txt", "fileA.txt"));
+        assert!(matches("snake[_-]case.rb", "snake_case.rb"));
+        assert!(matches("snake[_-]case.rb", "snake-case.rb"));
+        assert!(matches("a[/]b", "a/b"));
+        assert!(matches("a+b.txt", "a+b.txt"));
+        assert!(!matches("**/*.rb", "rusty.rs"));
+        assert!(!matches("file[!0-9].txt", "file1.txt"));
+        assert!(!matches("snake[_-]case.rb", "snake.case.rb"));
+        assert!(!matches("a+b.txt", "aab.txt"));
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        assert!(!matches("a**/b", "a/b"));
+        assert!(!matches("file[0-9.txt", "file1.txt"));
+        assert!(!matches("regex:(", "("));
+    }
+
+    #[test]
+    fn test_negation_and_regex() {
+        assert!(matches("!vendor/**", "app/models/user.rb"));
+        assert!(!matches("!vendor/**", "vendor/gems/a.rb"));
+        assert!(matches(
+            r"regex:^db/migrate/\d+_.*\.rb$",
+            "db/migrate/001_init.rb"
+        ));
+        assert!(!matches(
+            r"regex:^db/migrate/\d+_.*\.rb$",
+            "db/migrate/init.rb"
+        ));
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let matcher = PathMatcher::new(vec![("*.md", false), ("*.md", true)]);
+        assert_eq!(matcher.matches(b"README.MD"), vec![false, true]);
+    }
+
+    #[test]
+    fn test_non_utf8_paths() {
+        assert!(PathMatcher::new(vec![("**/*.rb", false)]).matches(b"caf\xe9/app.rb")[0]);
+    }
+}
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+
+use linked_hash_map::LinkedHashMap;
+
+use crate::config::{Config, GlobConfig, MergeMode};
+use crate::working_config::WorkingConfig;
+
+/// A project within a monorepo, made up of the files under `path`. Its
+/// commits are analyzed as if it were a repo of its own named `name`.
+///
+/// The project's config is layered on top of the repo's. Its `files` globs
+/// are relative to `path`, like those of a `.codealong.yml` in that
+/// directory, on top of which they are layered if the repo has one, and all
+/// of its files are tagged with `tags`. Its `churn_cutoff` overrides the
+/// repo's, whereas `merge_defaults` is taken from the repo.
+///
+/// ```yaml
+/// repos:
+///   - name: acme/platform
+///     projects:
+///       - name: billing
+///         path: services/billing
+///         tags: ["payments"]
+///         churn_cutoff: 30
+///         files:
+///           "migrations/**/*":
+///             tags: ["migration"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectConfig {
+    pub name: String,
+
+    /// The directory of the project relative to the repository root.
+    pub path: String,
+
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// Overrides the repo's. Kept apart from `config`, whose `churn_cutoff`
+    /// cannot tell an unset value from the default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub churn_cutoff: Option<u64>,
+
+    #[serde(flatten)]
+    pub config: Config,
+}
+
+/// A project along with the config its commits are analyzed with.
+pub struct Project {
+    name: String,
+    prefix: String,
+    config: Arc<WorkingConfig>,
+}
+
+impl Project {
+    /// Builds the project's config on top of `repo_config`.
+    pub fn new(project_config: &ProjectConfig, repo_config: &Config) -> Project {
+        let dir = project_config.path.trim_matches('/').to_owned();
+        let mut config = project_config.config.clone();
+        let mut files = LinkedHashMap::new();
+        if !project_config.tags.is_empty() {
+            let glob_config = GlobConfig {
+                tags: project_config.tags.clone(),
+                ..GlobConfig::default()
+            };
+            files.insert("**/*".to_owned(), glob_config);
+        }
+        files.extend(std::mem::take(&mut config.files));
+        let mut directory_config = Config::default();
+        directory_config.files = files;
+        directory_config.merge_strategy.files = config.merge_strategy.files;
+        // the files of the project config only apply to the project's
+        // directory, which is dealt with above
+        config.merge_strategy.files = MergeMode::Append;
+        config.directories.insert(dir.clone(), directory_config);
+        let mut project_repo_config = repo_config.clone();
+        project_repo_config.merge(config);
+        if let Some(churn_cutoff) = project_config.churn_cutoff {
+            project_repo_config.churn_cutoff = churn_cutoff;
+        }
+        Project {
+            name: project_config.name.clone(),
+            prefix: format!("{}/", dir),
+            config: Arc::new(WorkingConfig::new(project_repo_config)),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn config(&self) -> &Arc<WorkingConfig> {
+        &self.config
+    }
+
+    /// Whether `path` is one of the project's files.
+    pub fn contains(&self, path: &str) -> bool {
+        path.starts_with(&self.prefix)
+    }
+}
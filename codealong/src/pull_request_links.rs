@@ -0,0 +1,140 @@
+use std::collections::{BTreeMap, HashMap};
+
+use git2::{Commit, Oid, Repository, Sort};
+use regex::Regex;
+
+use crate::error::*;
+
+/// The pull request each commit was merged through.
+pub struct PullRequestLinks {
+    links: HashMap<Oid, u64>,
+}
+
+impl PullRequestLinks {
+    /// Links the commits reachable from `tips` but not from `hide` using
+    /// their commit messages:
+    ///
+    /// * `Merge pull request #N` links the merge commit along with the
+    ///   commits it merged,
+    /// * a `(#N)` suffix, as added to squashed pull requests, links just that
+    ///   commit.
+    ///
+    /// `known` links commit ids to pull requests, e.g. from the commit lists
+    /// of pull requests fetched from GitHub, and takes precedence.
+    pub fn resolve(
+        repo: &Repository,
+        tips: &[Oid],
+        hide: &[Oid],
+        known: &BTreeMap<String, u64>,
+    ) -> Result<PullRequestLinks> {
+        let mut links = HashMap::new();
+        let mut revwalk = repo.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE);
+        for oid in tips {
+            revwalk.push(*oid)?;
+        }
+        for oid in hide {
+            revwalk.hide(*oid)?;
+        }
+        let mut merges = Vec::new();
+        for oid in revwalk {
+            let commit = repo.find_commit(oid?)?;
+            if let Some(number) = pull_request_number(&commit) {
+                links.insert(commit.id(), number);
+                if commit.parent_count() > 1 {
+                    merges.push((commit, number));
+                }
+            }
+        }
+        // Oldest first, so that commits of pull requests merged into the
+        // branch of another pull request stay linked to their own.
+        for (merge, number) in merges {
+            let mut revwalk = repo.revwalk()?;
+            for parent_id in merge.parent_ids().skip(1) {
+                revwalk.push(parent_id)?;
+            }
+            revwalk.hide(merge.parent_id(0)?)?;
+            for oid in hide {
+                revwalk.hide(*oid)?;
+            }
+            for oid in revwalk {
+                links.entry(oid?).or_insert(number);
+            }
+        }
+        for (commit_id, number) in known {
+            if let Ok(oid) = Oid::from_str(commit_id) {
+                links.insert(oid, *number);
+            }
+        }
+        Ok(PullRequestLinks { links })
+    }
+
+    /// No links, so that commits are not attributed to any pull request.
+    pub(crate) fn empty() -> PullRequestLinks {
+        PullRequestLinks {
+            links: HashMap::new(),
+        }
+    }
+
+    pub fn pull_request_of(&self, oid: Oid) -> Option<u64> {
+        self.links.get(&oid).cloned()
+    }
+}
+
+/// Parses the number of the pull request referenced by the summary of a merge
+/// or squash commit.
+fn pull_request_number(commit: &Commit) -> Option<u64> {
+    lazy_static! {
+        static ref MERGE_REGEX: Regex = Regex::new(r"^Merge pull request #(\d+)").unwrap();
+        static ref SQUASH_REGEX: Regex = Regex::new(r"\(#(\d+)\)$").unwrap();
+    }
+    let summary = commit.summary()?;
+    let regex: &Regex = if commit.parent_count() > 1 {
+        &MERGE_REGEX
+    } else {
+        &SQUASH_REGEX
+    };
+    regex
+        .captures(summary.trim_end())
+        .and_then(|captures| captures[1].parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/pull_requests")?;
+        let head = repo.head()?.peel_to_commit()?;
+        let mut known = BTreeMap::new();
+        known.insert(head.id().to_string(), 3);
+        let links = PullRequestLinks::resolve(&repo, &[head.id()], &[], &known)?;
+
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push(head.id())?;
+        let mut linked = revwalk
+            .map(|oid| {
+                let oid = oid?;
+                let summary = repo.find_commit(oid)?.summary().unwrap().to_owned();
+                Ok((summary, links.pull_request_of(oid)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        linked.sort();
+        assert_eq!(
+            linked,
+            vec![
+                ("Add feature".to_owned(), Some(1)),
+                ("Direct push".to_owned(), Some(3)),
+                ("Fix typo (#2)".to_owned(), Some(2)),
+                ("Initial commit".to_owned(), None),
+                (
+                    "Merge pull request #1 from alice/feature".to_owned(),
+                    Some(1)
+                ),
+                ("Polish feature".to_owned(), Some(1)),
+            ]
+        );
+        Ok(())
+    }
+}
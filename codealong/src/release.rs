@@ -0,0 +1,133 @@
+use chrono::prelude::*;
+use chrono::DateTime;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::iter::FromIterator;
+
+use crate::analyzed_commit::AnalyzedCommit;
+use crate::analyzed_diff::AnalyzedDiff;
+use crate::commit_update::{CommitUpdate, Released};
+use crate::contributor::Contributor;
+use crate::event::Event;
+use crate::repo_info::PartialRepoInfo;
+
+/// A release tag along with the work that went into it since the previous
+/// release.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Release {
+    pub id: String,
+    pub tag: String,
+    pub commit_id: String,
+
+    /// When the tag was created for annotated tags, otherwise when the tagged
+    /// commit was committed.
+    pub released_at: DateTime<Utc>,
+
+    pub previous_tag: Option<String>,
+    pub previous_released_at: Option<DateTime<Utc>>,
+    pub seconds_since_previous: Option<i64>,
+
+    /// The commits contained in this release but not in any previous one.
+    pub commits: Vec<String>,
+    pub commit_count: usize,
+    pub contributors: Vec<Contributor>,
+
+    /// The time from authoring the commits of this release until it was
+    /// released.
+    pub mean_lead_time_seconds: Option<i64>,
+    pub median_lead_time_seconds: Option<i64>,
+    pub max_lead_time_seconds: Option<i64>,
+
+    /// The lead time of each commit.
+    #[serde(skip)]
+    lead_times: Vec<i64>,
+
+    /// The updates setting the release time of each commit of this release,
+    /// or of each of their parts in monorepos. The commits have usually been
+    /// indexed before they were released.
+    #[serde(skip)]
+    pub commit_updates: Vec<CommitUpdate<Released>>,
+
+    #[serde(flatten)]
+    pub diff: AnalyzedDiff,
+
+    pub repo: Option<PartialRepoInfo>,
+}
+
+impl Release {
+    pub fn new(tag: &str, commit_id: &str, released_at: DateTime<Utc>) -> Release {
+        Release {
+            id: format!("{}@{}", tag, commit_id),
+            tag: tag.to_owned(),
+            commit_id: commit_id.to_owned(),
+            released_at,
+            previous_tag: None,
+            previous_released_at: None,
+            seconds_since_previous: None,
+            commits: Vec::new(),
+            commit_count: 0,
+            contributors: Vec::new(),
+            mean_lead_time_seconds: None,
+            median_lead_time_seconds: None,
+            max_lead_time_seconds: None,
+            lead_times: Vec::new(),
+            commit_updates: Vec::new(),
+            diff: AnalyzedDiff::empty(),
+            repo: None,
+        }
+    }
+
+    pub fn set_previous(&mut self, tag: &str, released_at: DateTime<Utc>) {
+        self.previous_tag = Some(tag.to_owned());
+        self.previous_released_at = Some(released_at);
+        self.seconds_since_previous = Some((self.released_at - released_at).num_seconds());
+    }
+
+    pub fn add_commit(&mut self, commit: &AnalyzedCommit) {
+        self.commits.push(commit.id.clone());
+        self.commit_count += 1;
+        self.diff += &commit.diff;
+        self.lead_times
+            .push((self.released_at - commit.authored_at).num_seconds());
+        if let Some(ref author) = commit.normalized_author {
+            if !self.contributors.iter().any(|c| c.id == author.id) {
+                self.contributors.push(author.clone());
+            }
+        }
+    }
+
+    /// Computes the lead time stats once all commits have been added.
+    pub fn finish(&mut self) {
+        self.lead_times.sort();
+        let len = self.lead_times.len();
+        if len == 0 {
+            return;
+        }
+        let total: i64 = self.lead_times.iter().sum();
+        self.mean_lead_time_seconds = Some(total / len as i64);
+        self.median_lead_time_seconds = Some(if len % 2 == 0 {
+            (self.lead_times[len / 2 - 1] + self.lead_times[len / 2]) / 2
+        } else {
+            self.lead_times[len / 2]
+        });
+        self.max_lead_time_seconds = self.lead_times.last().cloned();
+    }
+}
+
+impl Event for Release {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.released_at
+    }
+
+    fn event_type(&self) -> &str {
+        "release"
+    }
+
+    fn id(&self) -> Cow<str> {
+        Cow::Borrowed(&self.id)
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        HashSet::from_iter(self.diff.tag_stats.keys().map(|s| s.to_owned()))
+    }
+}
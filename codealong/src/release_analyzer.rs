@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+
+use chrono::prelude::*;
+use chrono::DateTime;
+use git2::{Oid, Repository};
+use glob::Pattern;
+
+use crate::analyze_opts::AnalyzeOpts;
+use crate::commit_update::{CommitUpdate, Released};
+use crate::error::*;
+use crate::release::Release;
+use crate::repo_analyzer::RepoAnalyzer;
+use crate::repo_info::RepoInfo;
+use crate::utils::convert_time;
+
+/// A tag considered a release.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReleaseTag {
+    pub name: String,
+    pub oid: Oid,
+    pub released_at: DateTime<Utc>,
+}
+
+/// The release tags of a repository along with the first release each commit
+/// was shipped in.
+pub struct Releases {
+    tags: Vec<ReleaseTag>,
+    release_of: HashMap<Oid, usize>,
+}
+
+impl Releases {
+    /// Walks the history of each tag matching the repo's `release_tags`,
+    /// oldest first, so that commits are attributed to the earliest release
+    /// containing them.
+    pub fn resolve(repo: &Repository, repo_info: &RepoInfo) -> Result<Releases> {
+        let tags = release_tags(repo, &repo_info.release_tags)?;
+        let mut release_of = HashMap::new();
+        for (index, tag) in tags.iter().enumerate() {
+            let mut revwalk = repo.revwalk()?;
+            revwalk.push(tag.oid)?;
+            for earlier in &tags[..index] {
+                revwalk.hide(earlier.oid)?;
+            }
+            for oid in revwalk {
+                release_of.entry(oid?).or_insert(index);
+            }
+        }
+        Ok(Releases { tags, release_of })
+    }
+
+    /// No releases, so that commits are not attributed to any.
+    pub(crate) fn empty() -> Releases {
+        Releases {
+            tags: Vec::new(),
+            release_of: HashMap::new(),
+        }
+    }
+
+    /// The first release containing `oid`, if it has been released.
+    pub fn release_of(&self, oid: Oid) -> Option<&ReleaseTag> {
+        self.release_of.get(&oid).map(|index| &self.tags[*index])
+    }
+}
+
+/// Iterates over the releases of a repository, oldest first. Each release
+/// holds the commits reachable from its tag but not from any earlier release,
+/// whose stats are rebuilt from the analysis cache where possible.
+pub struct ReleaseAnalyzer<'a> {
+    repo_analyzer: &'a RepoAnalyzer,
+    tags: Vec<ReleaseTag>,
+    index: usize,
+    opts: AnalyzeOpts,
+}
+
+impl<'a> ReleaseAnalyzer<'a> {
+    pub fn new(repo_analyzer: &'a RepoAnalyzer, opts: AnalyzeOpts) -> Result<ReleaseAnalyzer<'a>> {
+        let tags = release_tags(
+            repo_analyzer.repository(),
+            &repo_analyzer.repo_info().release_tags,
+        )?;
+        Ok(ReleaseAnalyzer {
+            repo_analyzer,
+            tags,
+            index: 0,
+            opts,
+        })
+    }
+
+    pub fn tags(&self) -> &[ReleaseTag] {
+        &self.tags
+    }
+
+    fn analyze_release(&self, index: usize) -> Result<Release> {
+        let tag = &self.tags[index];
+        let mut release = Release::new(&tag.name, &tag.oid.to_string(), tag.released_at);
+        if index > 0 {
+            let previous = &self.tags[index - 1];
+            release.set_previous(&previous.name, previous.released_at);
+        }
+        let hide: Vec<Oid> = self.tags[..index].iter().map(|t| t.oid).collect();
+        for commit_analyzer in self
+            .repo_analyzer
+            .commits_between(tag.oid, &hide, &self.opts)?
+        {
+            let commit_analyzer = commit_analyzer?;
+            let analyzed_commit = commit_analyzer.analyze_stats()?;
+            for project in commit_analyzer.part_projects()? {
+                let released = Released::new(tag.released_at, analyzed_commit.authored_at);
+                let mut commit_update = CommitUpdate::new(commit_analyzer.commit(), released);
+                commit_update.project = project;
+                release.commit_updates.push(commit_update);
+            }
+            release.add_commit(&analyzed_commit);
+        }
+        release.finish();
+        release.repo = Some(self.repo_analyzer.repo_info().partial());
+        Ok(release)
+    }
+}
+
+impl<'a> Iterator for ReleaseAnalyzer<'a> {
+    type Item = Result<Release>;
+
+    fn next(&mut self) -> Option<Result<Release>> {
+        while self.index < self.tags.len() {
+            let index = self.index;
+            self.index += 1;
+            if self.opts.state.releases.contains(&self.tags[index].name) {
+                continue;
+            }
+            return Some(self.analyze_release(index));
+        }
+        None
+    }
+}
+
+/// Lists the tags whose names match any of `patterns`, or all tags if there
+/// are none, ordered by release time.
+pub fn release_tags(repo: &Repository, patterns: &[String]) -> Result<Vec<ReleaseTag>> {
+    let patterns = patterns
+        .iter()
+        .map(|pattern| Pattern::new(pattern))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let mut tags = Vec::new();
+    for reference in repo.references_glob("refs/tags/*")? {
+        let reference = reference?;
+        let name = match reference.shorthand() {
+            Some(name) => name.to_owned(),
+            None => continue,
+        };
+        if !patterns.is_empty() && !patterns.iter().any(|p| p.matches(&name)) {
+            continue;
+        }
+        // tags of trees or blobs are not releases
+        let commit = match reference.peel_to_commit() {
+            Ok(commit) => commit,
+            Err(_) => continue,
+        };
+        let released_at = match reference.peel_to_tag() {
+            Ok(tag) => match tag.tagger() {
+                Some(tagger) => convert_time(&tagger.when()),
+                None => convert_time(&commit.committer().when()),
+            },
+            Err(_) => convert_time(&commit.committer().when()),
+        };
+        tags.push(ReleaseTag {
+            name,
+            oid: commit.id(),
+            released_at,
+        });
+    }
+    tags.sort_by(|a, b| {
+        a.released_at
+            .cmp(&b.released_at)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    Ok(tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo_config::RepoConfig;
+    use crate::repo_info::RepoInfo;
+    use crate::test::build_test_logger;
+
+    fn build_analyzer(release_tags: Vec<String>) -> Result<RepoAnalyzer> {
+        let repo = Repository::open("./fixtures/repos/releases")?;
+        let config = RepoConfig {
+            repo: RepoInfo {
+                name: "releases".to_owned(),
+                release_tags,
+                ..RepoInfo::default()
+            },
+            ..RepoConfig::default()
+        };
+        Ok(RepoAnalyzer::new(repo, config, &build_test_logger()))
+    }
+
+    #[test]
+    fn test_release_tags() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/releases")?;
+        let names = |patterns: &[&str]| -> Result<Vec<String>> {
+            let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+            Ok(release_tags(&repo, &patterns)?
+                .into_iter()
+                .map(|t| t.name)
+                .collect())
+        };
+        assert_eq!(names(&[])?, vec!["v1.0", "nightly", "v1.1"]);
+        assert_eq!(names(&["v*"])?, vec!["v1.0", "v1.1"]);
+        let tags = release_tags(&repo, &[])?;
+        // annotated tags are released when tagged
+        assert_eq!(tags[0].released_at, Utc.ymd(2019, 1, 5).and_hms(12, 0, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_analyze_releases() -> Result<()> {
+        let analyzer = build_analyzer(vec!["v*".to_owned()])?;
+        let releases = analyzer
+            .analyze_releases(AnalyzeOpts::default())?
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(releases.len(), 2);
+
+        let first = &releases[0];
+        assert_eq!(first.tag, "v1.0");
+        assert_eq!(first.commit_count, 1);
+        assert_eq!(first.previous_tag, None);
+        assert_eq!(first.diff.stats.new_work, 3);
+
+        let second = &releases[1];
+        assert_eq!(second.tag, "v1.1");
+        assert_eq!(second.commit_count, 2);
+        assert_eq!(second.previous_tag, Some("v1.0".to_owned()));
+        assert_eq!(second.seconds_since_previous, Some(7 * 24 * 60 * 60));
+        let mut contributors: Vec<&str> =
+            second.contributors.iter().map(|c| c.id.as_str()).collect();
+        contributors.sort();
+        assert_eq!(
+            contributors,
+            vec!["Alice <alice@example.com>", "Bob <bob@example.com>"]
+        );
+
+        let mut opts = AnalyzeOpts::default();
+        opts.state.releases.insert("v1.0".to_owned());
+        let tags: Vec<String> = analyzer
+            .analyze_releases(opts)?
+            .map(|release| release.map(|r| r.tag))
+            .collect::<Result<_>>()?;
+        assert_eq!(tags, vec!["v1.1"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lead_time() -> Result<()> {
+        let analyzer = build_analyzer(vec!["v*".to_owned()])?;
+        let mut lead_times = analyzer
+            .analyze(AnalyzeOpts::default())?
+            .map(|commit_analyzer| {
+                let analyzed_commit = commit_analyzer?.analyze()?;
+                Ok((
+                    analyzed_commit.summary.unwrap(),
+                    analyzed_commit.released_at,
+                    analyzed_commit.lead_time_seconds,
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        lead_times.sort();
+        let day = 24 * 60 * 60;
+        let v1_0 = Some(Utc.ymd(2019, 1, 5).and_hms(12, 0, 0));
+        let v1_1 = Some(Utc.ymd(2019, 1, 12).and_hms(12, 0, 0));
+        assert_eq!(
+            lead_times,
+            vec![
+                ("Add a".to_owned(), v1_0, Some(4 * day)),
+                ("Add b".to_owned(), v1_1, Some(2 * day)),
+                ("Add c".to_owned(), None, None),
+                ("Change a".to_owned(), v1_1, Some(0)),
+            ]
+        );
+
+        let releases = analyzer
+            .analyze_releases(AnalyzeOpts::default())?
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(releases[1].mean_lead_time_seconds, Some(day));
+        assert_eq!(releases[1].median_lead_time_seconds, Some(day));
+        assert_eq!(releases[1].max_lead_time_seconds, Some(2 * day));
+        let mut updated_lead_times: Vec<i64> = releases[1]
+            .commit_updates
+            .iter()
+            .map(|commit_update| commit_update.fields.lead_time_seconds)
+            .collect();
+        updated_lead_times.sort();
+        assert_eq!(updated_lead_times, vec![0, 2 * day]);
+        assert_eq!(
+            releases[1].commit_updates[0].fields.released_at,
+            v1_1.unwrap()
+        );
+        Ok(())
+    }
+}
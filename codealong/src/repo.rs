@@ -1,10 +1,12 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use git2::build::{CheckoutBuilder, RepoBuilder};
 use git2::{FetchOptions, RemoteCallbacks, Repository};
 
+use crate::branches::default_branch;
 use crate::config::Config;
 use crate::error::*;
+use crate::project::ProjectConfig;
 use crate::repo_config::RepoConfig;
 use crate::repo_info::RepoInfo;
 use crate::utils::with_authentication;
@@ -16,6 +18,7 @@ pub struct Repo {
     base_config: Config,
     path: PathBuf,
     repo_info: RepoInfo,
+    projects: Vec<ProjectConfig>,
 }
 
 impl Repo {
@@ -24,6 +27,7 @@ impl Repo {
             base_config,
             path,
             repo_info,
+            projects: Vec::new(),
         }
     }
 
@@ -98,24 +102,59 @@ impl Repo {
         })?)
     }
 
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
     pub fn repo_info(&self) -> &RepoInfo {
         &self.repo_info
     }
 
-    /// Combines base config with any config found in the repo itself
-    pub fn config(&self) -> RepoConfig {
-        // TODO once we go to bare repos we need to
-        // read the object directly from git
-        RepoConfig {
-            repo: self.repo_info().clone(),
-            config: self.base_config.clone(),
+    /// The projects the repo is split into if it is a monorepo, from its
+    /// workspace entry.
+    pub fn projects(&self) -> &[ProjectConfig] {
+        &self.projects
+    }
+
+    pub fn set_projects(&mut self, projects: Vec<ProjectConfig>) {
+        self.projects = projects;
+    }
+
+    /// The config this repo was created with, before any in-repo config is
+    /// applied.
+    pub fn base_config(&self) -> &Config {
+        &self.base_config
+    }
+
+    /// Combines base config with any config found in the repo itself.
+    ///
+    /// The in-repo `.codealong.yml` is read from the default branch, or else
+    /// the first of the repo's refs which exists, and is layered on top of
+    /// the base config according to its own `merge_strategy`, so repo owners
+    /// can maintain their own tags and weights. Scalar settings such as
+    /// `churn_cutoff` always come from the base config, whereas optional ones
+    /// such as `module_depth` are taken from the in-repo config when set.
+    pub fn config(&self) -> Result<RepoConfig> {
+        let mut config = self.base_config.clone();
+        let repository = self.repository()?;
+        let mut refs: Vec<String> = default_branch(&repository, &self.repo_info)
+            .into_iter()
+            .collect();
+        refs.extend(self.repo_info.refs.iter().cloned());
+        if let Some(repo_config) = RepoConfig::config_from_refs(&repository, &refs)? {
+            config.merge(repo_config);
         }
+        Ok(RepoConfig {
+            repo: self.repo_info().clone(),
+            config,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::GlobConfig;
 
     #[test]
     fn test_clone() -> Result<()> {
@@ -130,4 +169,31 @@ mod tests {
         repo.clone_repo(None)?;
         Ok(())
     }
+
+    #[test]
+    fn test_config() -> Result<()> {
+        let mut config = Config::default();
+        config.churn_cutoff = 7;
+        config.files.insert(
+            "**/*.md".to_owned(),
+            GlobConfig {
+                tags: vec!["docs".to_owned()],
+                weight: 1.0,
+                ignore: false,
+                ..GlobConfig::default()
+            },
+        );
+        let repo_info = RepoInfo {
+            name: "bare_config".to_owned(),
+            ..Default::default()
+        };
+        let repo = Repo::new(config, "./fixtures/repos/bare_config".into(), repo_info);
+        let config = repo.config()?.config;
+        assert_eq!(config.churn_cutoff, 7);
+        assert_eq!(
+            config.files.keys().collect::<Vec<_>>(),
+            vec!["**/*.md", "README.md"]
+        );
+        Ok(())
+    }
 }
@@ -1,11 +1,36 @@
-use git2::{Repository, Revwalk};
+use git2::{Commit, Oid, Repository, Revwalk};
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
 
+use crate::analysis_cache::AnalysisCache;
+use crate::analysis_state::RepoState;
 use crate::analyze_opts::AnalyzeOpts;
+use crate::analyzed_commit::AnalyzedCommit;
+use crate::branches::{default_branch, Branches};
+use crate::bus_factor::{BusFactor, BusFactorOpts};
+use crate::code_owners_cache::CodeOwnersCache;
 use crate::commit_analyzer::CommitAnalyzer;
+use crate::commit_update::{BugIntroducing, CommitUpdate, Reverted, Reviewed};
+use crate::config::Config;
+use crate::config_cache::ConfigCache;
+use crate::coupling::{Coupling, CouplingOpts};
+use crate::coupling_analyzer::CouplingAnalyzer;
 use crate::error::*;
+use crate::hotspot::{Hotspot, HotspotOpts};
+use crate::hotspot_analyzer::HotspotAnalyzer;
+use crate::line_survival::{LineSurvival, LineSurvivalOpts};
+use crate::line_survival_analyzer::LineSurvivalAnalyzer;
+use crate::ownership::Ownership;
+use crate::ownership_analyzer::OwnershipAnalyzer;
+use crate::parallel_analyzer::{ParallelAnalysis, WorkerBudget};
+use crate::project::{Project, ProjectConfig};
+use crate::pull_request_links::PullRequestLinks;
+use crate::release_analyzer::{ReleaseAnalyzer, Releases};
 use crate::repo::Repo;
 use crate::repo_config::RepoConfig;
 use crate::repo_info::RepoInfo;
+use crate::reverts::Reverts;
 use crate::slog::Logger;
 use crate::utils::convert_time;
 use crate::working_config::WorkingConfig;
@@ -13,60 +38,420 @@ use crate::working_config::WorkingConfig;
 pub struct RepoAnalyzer {
     repo: Repository,
     config: RepoConfig,
-    working_config: WorkingConfig,
+    working_config: Arc<WorkingConfig>,
+    project_configs: Vec<ProjectConfig>,
+    projects: Arc<Vec<Project>>,
+    base_config: Config,
     logger: Logger,
 }
 
 impl RepoAnalyzer {
     pub fn new(repo: Repository, config: RepoConfig, parent_logger: &Logger) -> RepoAnalyzer {
-        let working_config = WorkingConfig::new(config.config.clone());
+        let working_config = Arc::new(WorkingConfig::new(config.config.clone()));
         RepoAnalyzer {
             repo,
             working_config,
+            project_configs: Vec::new(),
+            projects: Arc::new(Vec::new()),
+            base_config: Config::default(),
             logger: parent_logger.new(o!("repo" => config.repo.name.to_owned())),
             config,
         }
     }
 
+    /// Sets the config which in-repo configs are layered on top of when
+    /// analyzing with `historical_config`, i.e. the workspace config.
+    /// Defaults to an empty workspace config, as `config` already includes
+    /// the in-repo config as of the default branch.
+    pub fn set_base_config(&mut self, base_config: Config) {
+        self.base_config = base_config;
+    }
+
+    /// Sets the projects commits are split by. Their configs are layered on
+    /// top of the repo's config, or that of each commit when analyzing with
+    /// `historical_config`.
+    pub fn set_projects(&mut self, project_configs: Vec<ProjectConfig>) {
+        let projects = project_configs
+            .iter()
+            .map(|project_config| Project::new(project_config, &self.config.config))
+            .collect();
+        self.projects = Arc::new(projects);
+        self.project_configs = project_configs;
+    }
+
     pub fn analyze(
         &self,
         opts: AnalyzeOpts,
     ) -> Result<impl Iterator<Item = Result<CommitAnalyzer>>> {
+        self.walk(opts, true)
+    }
+
+    /// Analyzes every commit reachable from the configured refs once.
+    /// Attributing commits to branches, releases, pull requests and reverts
+    /// requires walking the history of each ref and release tag up front,
+    /// which is skipped unless `with_branches` is set.
+    fn walk(&self, opts: AnalyzeOpts, with_branches: bool) -> Result<AnalyzedRevwalk> {
+        let mut branches = Branches::resolve(&self.repo, &self.config.repo, &self.logger)?;
         let mut revwalk = self.repo.revwalk()?;
-        for reference in &self.config.repo.refs {
-            if let Ok(_) = self.repo.find_reference(reference) {
-                revwalk.push_ref(&reference)?;
-            } else {
-                warn!(
-                    self.logger,
-                    "Could not find reference: {}, using HEAD", reference
-                );
-                revwalk.push_head()?;
+        for reference in branches.refs() {
+            revwalk.push(reference.oid)?;
+        }
+        let mut hidden = Vec::new();
+        for commit_id in opts.state.refs.values() {
+            // Commits which are no longer around, e.g. after a force push,
+            // cannot be hidden and their ancestors are analyzed again.
+            match Oid::from_str(commit_id).and_then(|oid| revwalk.hide(oid).map(|_| oid)) {
+                Ok(oid) => hidden.push(oid),
+                Err(e) => {
+                    let error = e.to_string();
+                    warn!(self.logger, "Could not skip analyzed commit: {}", commit_id; "error" => error);
+                }
+            }
+        }
+        let history = if with_branches {
+            branches.compute_reachability(&self.repo, &hidden)?;
+            let tips: Vec<Oid> = branches.refs().iter().map(|r| r.oid).collect();
+            History {
+                releases: Releases::resolve(&self.repo, &self.config.repo)?,
+                pull_requests: PullRequestLinks::resolve(
+                    &self.repo,
+                    &tips,
+                    &hidden,
+                    &opts.state.pull_request_commits.clone().unwrap_or_default(),
+                )?,
+                reverts: Reverts::resolve(&self.repo, &tips, &hidden, opts.since)?,
             }
+        } else {
+            History::empty()
+        };
+        Ok(self.analyzed_revwalk(revwalk, branches, history, opts))
+    }
+
+    /// Analyzes the commits reachable from `tip` but not from any of `hide`,
+    /// regardless of the `since`, `ignore_unknown_authors` and `state` opts.
+    pub(crate) fn commits_between(
+        &self,
+        tip: Oid,
+        hide: &[Oid],
+        opts: &AnalyzeOpts,
+    ) -> Result<AnalyzedRevwalk> {
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.push(tip)?;
+        for oid in hide {
+            revwalk.hide(*oid)?;
         }
-        Ok(AnalyzedRevwalk {
+        let opts = AnalyzeOpts {
+            since: None,
+            ignore_unknown_authors: false,
+            state: RepoState::default(),
+            ..opts.clone()
+        };
+        Ok(self.analyzed_revwalk(revwalk, Branches::empty(), History::empty(), opts))
+    }
+
+    fn analyzed_revwalk<'a>(
+        &'a self,
+        revwalk: Revwalk<'a>,
+        branches: Branches,
+        history: History,
+        opts: AnalyzeOpts,
+    ) -> AnalyzedRevwalk<'a> {
+        let cache = if opts.cache {
+            Some(Arc::new(AnalysisCache::for_repository(&self.repo)))
+        } else {
+            None
+        };
+        let config_cache = if opts.historical_config {
+            Some(ConfigCache::new(
+                self.base_config.clone(),
+                self.project_configs.clone(),
+            ))
+        } else {
+            None
+        };
+        AnalyzedRevwalk {
             repo: &self.repo,
+            code_owners_cache: Arc::new(CodeOwnersCache::new()),
+            submodule_repos: Arc::new(opts.submodule_repos.clone()),
+            projects: self.projects.clone(),
             revwalk,
-            config: &self.working_config,
+            config: self.working_config.clone(),
+            config_cache,
+            cache,
+            branches,
+            history,
             repo_info: &self.config.repo,
             opts,
             logger: self.logger.clone(),
+        }
+    }
+
+    /// Emits a release for each tag matching the repo's `release_tags`,
+    /// aggregating the commits since the previous release.
+    pub fn analyze_releases(&self, opts: AnalyzeOpts) -> Result<ReleaseAnalyzer> {
+        ReleaseAnalyzer::new(self, opts)
+    }
+
+    /// Blames every file at `reference`, or the default branch if not given,
+    /// to find who owns which lines.
+    pub fn analyze_ownership(&self, reference: Option<&str>) -> Result<Vec<Ownership>> {
+        let commit = self.snapshot_commit(reference)?;
+        self.ownership_analyzer().analyze(&commit)
+    }
+
+    /// Computes the bus factor of the repository root and of each top-level
+    /// directory at `reference`, or the default branch if not given.
+    /// Contributors are active if they committed to any of the analyzed refs
+    /// recently.
+    pub fn analyze_bus_factor(
+        &self,
+        reference: Option<&str>,
+        opts: &BusFactorOpts,
+    ) -> Result<Vec<BusFactor>> {
+        let commit = self.snapshot_commit(reference)?;
+        let analyzer = self.ownership_analyzer();
+        let branches = Branches::resolve(&self.repo, &self.config.repo, &self.logger)?;
+        let mut tips: Vec<Oid> = branches.refs().iter().map(|r| r.oid).collect();
+        tips.push(commit.id());
+        let last_active_at = analyzer.last_active_at(&tips)?;
+        Ok(analyzer
+            .analyze(&commit)?
+            .iter()
+            .filter(|ownership| ownership.directory && !ownership.path.contains('/'))
+            .map(|ownership| BusFactor::new(ownership, &last_active_at, opts))
+            .collect())
+    }
+
+    /// Measures how many of the lines added in the history of `reference`, or
+    /// the default branch if not given, survive at each of the horizons.
+    pub fn analyze_line_survival(
+        &self,
+        reference: Option<&str>,
+        opts: &LineSurvivalOpts,
+    ) -> Result<Vec<LineSurvival>> {
+        let commit = self.snapshot_commit(reference)?;
+        let analyzer = LineSurvivalAnalyzer::new(
+            &self.repo,
+            &self.working_config,
+            &self.config.repo,
+            &self.logger,
+        );
+        analyzer.analyze(&commit, opts)
+    }
+
+    /// Ranks the files at `reference`, or the default branch if not given, by
+    /// how often they changed within the window times their size.
+    pub fn analyze_hotspots(
+        &self,
+        reference: Option<&str>,
+        opts: &HotspotOpts,
+    ) -> Result<Vec<Hotspot>> {
+        let commit = self.snapshot_commit(reference)?;
+        let analyzer = HotspotAnalyzer::new(&self.repo, &self.working_config, &self.config.repo);
+        analyzer.analyze(&commit, opts)
+    }
+
+    /// Finds the files and directories which tend to change together in the
+    /// history of `reference`, or the default branch if not given.
+    pub fn analyze_coupling(
+        &self,
+        reference: Option<&str>,
+        opts: &CouplingOpts,
+    ) -> Result<Vec<Coupling>> {
+        let commit = self.snapshot_commit(reference)?;
+        let analyzer = CouplingAnalyzer::new(&self.repo, &self.working_config, &self.config.repo);
+        analyzer.analyze(&commit, opts)
+    }
+
+    fn snapshot_commit(&self, reference: Option<&str>) -> Result<Commit> {
+        Ok(match reference {
+            Some(reference) => self.repo.revparse_single(reference)?.peel_to_commit()?,
+            None => match default_branch(&self.repo, &self.config.repo) {
+                Some(name) => self.repo.find_reference(&name)?.peel_to_commit()?,
+                None => self.repo.head()?.peel_to_commit()?,
+            },
         })
     }
 
+    fn ownership_analyzer(&self) -> OwnershipAnalyzer {
+        OwnershipAnalyzer::new(
+            &self.repo,
+            &self.working_config,
+            &self.config.repo,
+            &self.logger,
+        )
+    }
+
+    pub(crate) fn repository(&self) -> &Repository {
+        &self.repo
+    }
+
+    pub(crate) fn repo_info(&self) -> &RepoInfo {
+        &self.config.repo
+    }
+
+    /// Same as `analyze`, but analyzes the commits on a pool of worker
+    /// threads drawn from `budget` and yields the analyzed commits in the
+    /// same order. Commits of repos with `projects` are split by project, see
+    /// `CommitAnalyzer::analyze_projects`.
+    pub fn analyze_parallel(
+        &self,
+        opts: AnalyzeOpts,
+        budget: Arc<WorkerBudget>,
+    ) -> Result<ParallelAnalysis> {
+        ParallelAnalysis::new(
+            &self.repo,
+            self.analyze(opts)?,
+            &self.config.repo,
+            budget,
+            &self.logger,
+        )
+    }
+
+    /// The commit each analyzed ref currently points to, suitable for
+    /// recording in `RepoState::refs` once the analysis has completed.
+    pub fn ref_tips(&self) -> Result<BTreeMap<String, String>> {
+        let branches = Branches::resolve(&self.repo, &self.config.repo, &self.logger)?;
+        Ok(branches
+            .refs()
+            .iter()
+            .map(|reference| (reference.name.clone(), reference.oid.to_string()))
+            .collect())
+    }
+
+    /// The update marking the commit reverted by `revert` as such, if it can
+    /// be found.
+    pub fn reverted_commit(&self, revert: &AnalyzedCommit) -> Option<CommitUpdate<Reverted>> {
+        let original = revert.reverts.as_ref()?;
+        let commit = self.repo.find_commit(Oid::from_str(original).ok()?).ok()?;
+        let mut reverted_commit = CommitUpdate::new(&commit, Reverted::new(&revert.id));
+        // a revert changes the same files, and hence projects, as the
+        // commit it reverts
+        reverted_commit.project = revert.project.clone();
+        Some(reverted_commit)
+    }
+
+    /// The updates marking the commits which introduced the bug fixed by
+    /// `fix` as such, for those which can be found.
+    pub fn bug_introducing_commits(
+        &self,
+        fix: &AnalyzedCommit,
+    ) -> Vec<CommitUpdate<BugIntroducing>> {
+        fix.bug_introducing_commits
+            .iter()
+            .filter_map(|id| Oid::from_str(id).ok())
+            .filter_map(|oid| self.repo.find_commit(oid).ok())
+            .map(|commit| {
+                let mut bug_introducing_commit =
+                    CommitUpdate::new(&commit, BugIntroducing::new(&fix.id));
+                bug_introducing_commit.project = fix.project.clone();
+                bug_introducing_commit
+            })
+            .collect()
+    }
+
+    /// The updates linking the commits of pull request `number` to it, for
+    /// those which can be found.
+    pub fn reviewed_commits(
+        &self,
+        commit_ids: &[String],
+        number: u64,
+    ) -> Result<Vec<CommitUpdate<Reviewed>>> {
+        let mut commit_updates = Vec::new();
+        for commit_id in commit_ids {
+            let commit = match Oid::from_str(commit_id).and_then(|oid| self.repo.find_commit(oid)) {
+                Ok(commit) => commit,
+                Err(_) => continue,
+            };
+            let mut analyzer = CommitAnalyzer::new(
+                &self.repo,
+                commit.clone(),
+                self.working_config.clone(),
+                &self.config.repo,
+                &self.logger,
+            );
+            analyzer.set_projects(self.projects.clone());
+            for project in analyzer.part_projects()? {
+                let mut commit_update = CommitUpdate::new(&commit, Reviewed::new(number));
+                commit_update.project = project;
+                commit_updates.push(commit_update);
+            }
+        }
+        Ok(commit_updates)
+    }
+
+    /// Drops the commits reachable from the refs recorded in `state`, which
+    /// have been analyzed already, from `pull_request_commits`. Commits which
+    /// cannot be found are kept, as they may still be fetched.
+    pub fn unanalyzed_pull_request_commits(
+        &self,
+        mut pull_request_commits: BTreeMap<String, u64>,
+        state: &RepoState,
+    ) -> Result<BTreeMap<String, u64>> {
+        let mut revwalk = self.repo.revwalk()?;
+        let mut found = HashSet::new();
+        for commit_id in pull_request_commits.keys() {
+            if let Ok(oid) = Oid::from_str(commit_id) {
+                if revwalk.push(oid).is_ok() {
+                    found.insert(oid);
+                }
+            }
+        }
+        for commit_id in state.refs.values() {
+            // refs which are no longer around hide nothing
+            let _ = Oid::from_str(commit_id).and_then(|oid| revwalk.hide(oid));
+        }
+        let mut unanalyzed = HashSet::new();
+        for oid in revwalk {
+            unanalyzed.insert(oid?);
+        }
+        pull_request_commits.retain(|commit_id, _| match Oid::from_str(commit_id) {
+            Ok(oid) => !found.contains(&oid) || unanalyzed.contains(&oid),
+            Err(_) => false,
+        });
+        Ok(pull_request_commits)
+    }
+
     pub fn guess_len(&self, opts: AnalyzeOpts) -> Result<usize> {
-        Ok(self.analyze(opts)?.count())
+        Ok(self.walk(opts, false)?.count())
     }
 
     pub fn from_repo(repo: &Repo, logger: &Logger) -> Result<Self> {
-        Ok(Self::new(repo.repository()?, repo.config(), logger))
+        let mut analyzer = Self::new(repo.repository()?, repo.config()?, logger);
+        analyzer.set_base_config(repo.base_config().clone());
+        analyzer.set_projects(repo.projects().to_vec());
+        Ok(analyzer)
+    }
+}
+
+/// What is known about commits from the history around them.
+struct History {
+    releases: Releases,
+    pull_requests: PullRequestLinks,
+    reverts: Reverts,
+}
+
+impl History {
+    fn empty() -> History {
+        History {
+            releases: Releases::empty(),
+            pull_requests: PullRequestLinks::empty(),
+            reverts: Reverts::empty(),
+        }
     }
 }
 
 pub struct AnalyzedRevwalk<'repo> {
     repo: &'repo Repository,
     revwalk: Revwalk<'repo>,
-    config: &'repo WorkingConfig,
+    config: Arc<WorkingConfig>,
+    config_cache: Option<ConfigCache>,
+    cache: Option<Arc<AnalysisCache>>,
+    code_owners_cache: Arc<CodeOwnersCache>,
+    submodule_repos: Arc<BTreeMap<String, PathBuf>>,
+    projects: Arc<Vec<Project>>,
+    branches: Branches,
+    history: History,
     repo_info: &'repo RepoInfo,
     opts: AnalyzeOpts,
     logger: Logger,
@@ -91,13 +476,40 @@ impl<'repo> Iterator for AnalyzedRevwalk<'repo> {
                         }
                     }
 
-                    let analyzer = CommitAnalyzer::new(
+                    let (config, projects) = match self.config_cache {
+                        Some(ref mut config_cache) => {
+                            match config_cache.configs_for_commit(self.repo, &commit) {
+                                Ok(configs) => configs,
+                                Err(e) => break Some(Err(e)),
+                            }
+                        }
+                        None => (self.config.clone(), self.projects.clone()),
+                    };
+
+                    let mut analyzer = CommitAnalyzer::new(
                         self.repo,
                         commit,
-                        self.config,
+                        config,
                         &self.repo_info,
                         &self.logger,
                     );
+                    if let Some(ref cache) = self.cache {
+                        analyzer.set_cache(cache.clone());
+                    }
+                    analyzer.set_code_owners_cache(self.code_owners_cache.clone());
+                    analyzer.set_blame_bug_fixes(self.opts.blame_bug_fixes);
+                    analyzer.set_submodule_repos(self.submodule_repos.clone());
+                    analyzer.set_projects(projects);
+                    let (branches, on_default_branch) = self.branches.branches_of(oid);
+                    analyzer.set_branches(branches, on_default_branch);
+                    let history = &self.history;
+                    let released_at = history.releases.release_of(oid).map(|r| r.released_at);
+                    analyzer.set_released_at(released_at);
+                    analyzer.set_pull_request_number(history.pull_requests.pull_request_of(oid));
+                    analyzer.set_reverts(
+                        history.reverts.reverts(oid),
+                        history.reverts.reverted_by(oid),
+                    );
 
                     if self.opts.ignore_unknown_authors && !analyzer.is_author_known() {
                         continue;
@@ -113,6 +525,7 @@ impl<'repo> Iterator for AnalyzedRevwalk<'repo> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::event::Event;
     use crate::repo_info::RepoInfo;
     use crate::test::build_test_logger;
 
@@ -129,6 +542,7 @@ mod tests {
         let opts = AnalyzeOpts {
             ignore_unknown_authors: false,
             since: None,
+            ..AnalyzeOpts::default()
         };
         let analyzer = RepoAnalyzer::new(repo, config, &build_test_logger());
         assert!(analyzer.analyze(opts)?.count() >= 4);
@@ -148,9 +562,220 @@ mod tests {
         let opts = AnalyzeOpts {
             ignore_unknown_authors: true,
             since: None,
+            ..AnalyzeOpts::default()
         };
         let analyzer = RepoAnalyzer::new(repo, config, &build_test_logger());
         assert_eq!(analyzer.analyze(opts)?.count(), 0);
         Ok(())
     }
+
+    #[test]
+    fn test_analyze_parallel() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/simple")?;
+        let config = RepoConfig {
+            repo: RepoInfo {
+                refs: vec!["refs/heads/master".to_owned()],
+                ..RepoInfo::default()
+            },
+            ..RepoConfig::default()
+        };
+        let analyzer = RepoAnalyzer::new(repo, config, &build_test_logger());
+        let serial = analyzer
+            .analyze(AnalyzeOpts::default())?
+            .map(|commit_analyzer| commit_analyzer?.analyze())
+            .collect::<Result<Vec<_>>>()?;
+        let parallel = analyzer
+            .analyze_parallel(AnalyzeOpts::default(), Arc::new(WorkerBudget::new(3)))?
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(serial.len(), parallel.len());
+        assert!(serial == parallel);
+        Ok(())
+    }
+
+    #[test]
+    fn test_projects() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/coupling")?;
+        let project = |name: &str, tags: &[&str]| ProjectConfig {
+            name: name.to_owned(),
+            path: format!("{}/", name),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            churn_cutoff: None,
+            config: Config::default(),
+        };
+        let config = RepoConfig {
+            repo: RepoInfo {
+                name: "acme/platform".to_owned(),
+                refs: vec!["refs/heads/master".to_owned()],
+                ..RepoInfo::default()
+            },
+            ..RepoConfig::default()
+        };
+        let mut analyzer = RepoAnalyzer::new(repo, config, &build_test_logger());
+        analyzer.set_projects(vec![project("src", &["backend"]), project("docs", &[])]);
+        let commits = analyzer
+            .analyze_parallel(AnalyzeOpts::default(), Arc::new(WorkerBudget::new(2)))?
+            .collect::<Result<Vec<_>>>()?;
+        let parts = |summary: &str| {
+            commits
+                .iter()
+                .filter(|commit| commit.summary.as_ref().map(|s| s.as_str()) == Some(summary))
+                .map(|commit| (commit.project.clone(), commit.diff.stats.new_work))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            parts("Initial commit"),
+            vec![
+                (Some("src".to_owned()), 2),
+                (Some("docs".to_owned()), 1),
+                (None, 1)
+            ]
+        );
+        assert_eq!(
+            parts("Document a"),
+            vec![(Some("src".to_owned()), 1), (Some("docs".to_owned()), 1)]
+        );
+        assert_eq!(parts("Change c"), vec![(None, 1)]);
+
+        let initial_commit: Vec<&AnalyzedCommit> = commits
+            .iter()
+            .filter(|commit| commit.summary.as_ref().unwrap() == "Initial commit")
+            .collect();
+        assert_eq!(
+            initial_commit[0].id(),
+            format!("{}:src", initial_commit[0].id)
+        );
+        assert_eq!(initial_commit[2].id(), initial_commit[2].id);
+        assert_eq!(
+            initial_commit[0].repo,
+            Some(analyzer.repo_info().project_partial("src"))
+        );
+        assert!(initial_commit[0].diff.tag_stats.contains_key("backend"));
+        assert!(!initial_commit[1].diff.tag_stats.contains_key("backend"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_incremental() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/historical_config")?;
+        let config = RepoConfig {
+            repo: RepoInfo {
+                refs: vec!["refs/heads/master".to_owned()],
+                ..RepoInfo::default()
+            },
+            ..RepoConfig::default()
+        };
+        let analyzer = RepoAnalyzer::new(repo, config, &build_test_logger());
+        let tips = analyzer.ref_tips()?;
+        assert_eq!(tips.keys().collect::<Vec<_>>(), vec!["refs/heads/master"]);
+
+        let mut opts = AnalyzeOpts::default();
+        opts.state.refs = tips;
+        assert_eq!(analyzer.analyze(opts.clone())?.count(), 0);
+
+        let parent = analyzer.analyze(AnalyzeOpts::default())?.nth(1).unwrap()?;
+        opts.state.refs.insert(
+            "refs/heads/master".to_owned(),
+            parent.commit().id().to_string(),
+        );
+        assert_eq!(analyzer.analyze(opts.clone())?.count(), 1);
+
+        opts.state
+            .refs
+            .insert("refs/heads/master".to_owned(), "deadbeef".repeat(5));
+        assert_eq!(analyzer.analyze(opts)?.count(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_branches() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/simple")?;
+        let config = RepoConfig {
+            repo: RepoInfo {
+                refs: vec!["refs/remotes/origin/*".to_owned()],
+                default_branch: Some("feature".to_owned()),
+                ..RepoInfo::default()
+            },
+            ..RepoConfig::default()
+        };
+        let analyzer = RepoAnalyzer::new(repo, config, &build_test_logger());
+        let mut commits = analyzer
+            .analyze(AnalyzeOpts::default())?
+            .map(|commit_analyzer| {
+                let analyzed_commit = commit_analyzer?.analyze()?;
+                Ok((
+                    analyzed_commit.summary.unwrap(),
+                    analyzed_commit.branches,
+                    analyzed_commit.on_default_branch,
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        commits.sort();
+        let both = vec!["feature".to_owned(), "master".to_owned()];
+        let master = vec!["master".to_owned()];
+        assert_eq!(
+            commits,
+            vec![
+                ("Change in separate branch".to_owned(), both.clone(), true),
+                ("Initial commit".to_owned(), both, true),
+                ("Merge branch 'feature'".to_owned(), master.clone(), false),
+                ("Simple addition".to_owned(), master, false),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_historical_config() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/historical_config")?;
+        let config = RepoConfig {
+            repo: RepoInfo {
+                refs: vec!["refs/heads/master".to_owned()],
+                ..RepoInfo::default()
+            },
+            ..RepoConfig::default()
+        };
+        let opts = AnalyzeOpts {
+            historical_config: true,
+            ..AnalyzeOpts::default()
+        };
+        let analyzer = RepoAnalyzer::new(repo, config, &build_test_logger());
+        let tags = analyzer
+            .analyze(opts)?
+            .map(|commit_analyzer| {
+                let analyzed_commit = commit_analyzer?.analyze()?;
+                let mut tags: Vec<String> =
+                    analyzed_commit.diff.tag_stats.keys().cloned().collect();
+                tags.sort();
+                Ok(tags)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            tags,
+            vec![vec!["docs-v2"], vec!["docs-v2"], vec!["docs-v1"]]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_unanalyzed_pull_request_commits() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/pull_requests")?;
+        let analyzer = RepoAnalyzer::new(repo, RepoConfig::default(), &build_test_logger());
+        let mut state = RepoState::default();
+        state.refs.insert(
+            "refs/heads/master".to_owned(),
+            "695fef818cd03348bcb83eb230923d7869f41c37".to_owned(),
+        );
+        let missing = "1111111111111111111111111111111111111111";
+        let mut pull_request_commits = BTreeMap::new();
+        pull_request_commits.insert("95bd795e6554f5567015edd454d2b0035b1c58ef".to_owned(), 3);
+        pull_request_commits.insert("df242b37401c248341e63d73dbd7392fb9c3e85e".to_owned(), 1);
+        pull_request_commits.insert(missing.to_owned(), 4);
+        let unanalyzed = analyzer.unanalyzed_pull_request_commits(pull_request_commits, &state)?;
+        let commit_ids: Vec<&str> = unanalyzed.keys().map(|id| id.as_str()).collect();
+        assert_eq!(
+            commit_ids,
+            vec![missing, "95bd795e6554f5567015edd454d2b0035b1c58ef"]
+        );
+        Ok(())
+    }
 }
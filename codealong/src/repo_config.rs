@@ -1,7 +1,8 @@
+use std::collections::HashMap;
 use std::fs::File;
 use std::path::Path;
 
-use git2::Repository;
+use git2::{ObjectType, Oid, Repository, Tree};
 
 use crate::config::Config;
 use crate::error::*;
@@ -19,6 +20,10 @@ pub struct RepoConfig {
 impl RepoConfig {
     pub const DEFAULT_PATH: &'static str = ".codealong.yml";
 
+    /// Location used by older versions of codealong. Only consulted when no
+    /// config exists at `DEFAULT_PATH`.
+    pub const LEGACY_PATH: &'static str = ".codealong/config.yml";
+
     pub fn exists(dir: &Path) -> bool {
         Path::new(dir).join(Self::DEFAULT_PATH).exists()
     }
@@ -32,9 +37,9 @@ impl RepoConfig {
         Ok(serde_yaml::from_reader::<_, RepoConfig>(file)?)
     }
 
-    /// Attempts to read the config from the conventional location within the
-    /// directory at `.codealong/config.yml`. If no config is found, fallback
-    /// to the base config.
+    /// Attempts to read the config committed to the repository containing
+    /// `path` at `HEAD`. If no config is found, fallback to the default
+    /// config.
     ///
     /// If the config has no `name`, then default to the name of the directory.
     pub fn from_dir(path: &Path) -> Result<Self> {
@@ -42,17 +47,119 @@ impl RepoConfig {
     }
 
     pub fn from_repository(repo: &Repository) -> Result<Self> {
-        // TODO once we go to bare repos we need to
-        // read the object directly from git
-        let path = repo.path();
-        let file_path = path.join(".codealong").join(Self::DEFAULT_PATH);
-        let mut config = if file_path.exists() {
-            Self::from_path(&file_path)?
-        } else {
-            Self::default()
+        let config = match repo.head() {
+            Ok(head) => Self::config_from_tree(repo, &head.peel_to_tree()?)?,
+            Err(_) => None,
+        };
+        Ok(RepoConfig {
+            config: config.unwrap_or_default(),
+            repo: RepoInfo::from_repository(&repo)?,
+        })
+    }
+
+    /// Reads the config committed to the repository at the first of `refs`
+    /// which exists, falling back to `HEAD`. The config is read directly from
+    /// the object database, so this also works for bare repositories.
+    pub fn config_from_refs(repo: &Repository, refs: &[String]) -> Result<Option<Config>> {
+        let reference = refs
+            .iter()
+            .filter_map(|reference| repo.find_reference(reference).ok())
+            .next();
+        let tree = match reference {
+            Some(reference) => reference.peel_to_tree()?,
+            None => match repo.head() {
+                Ok(head) => head.peel_to_tree()?,
+                Err(_) => return Ok(None),
+            },
+        };
+        Self::config_from_tree(repo, &tree)
+    }
+
+    /// Reads the config stored in `tree`, if any, along with the configs
+    /// committed in its subdirectories.
+    ///
+    /// `extends` entries are not followed for configs read from a tree since
+    /// they would point outside of the repository.
+    pub fn config_from_tree(repo: &Repository, tree: &Tree) -> Result<Option<Config>> {
+        let directories = Self::find_directory_config_blobs(repo, tree, &mut HashMap::new())?;
+        let config = match Self::find_config_blob(tree) {
+            Some(oid) => Self::config_from_blob(repo, oid)?,
+            None if directories.is_empty() => return Ok(None),
+            None => Config::default(),
         };
-        // TODO: merge this
-        config.repo = RepoInfo::from_repository(&repo)?;
+        Ok(Some(Self::with_directories(repo, config, &directories)?))
+    }
+
+    /// Adds the directory configs found by `find_directory_config_blobs` to
+    /// `config`.
+    pub fn with_directories(
+        repo: &Repository,
+        mut config: Config,
+        directories: &[(String, Oid)],
+    ) -> Result<Config> {
+        for (dir, oid) in directories {
+            config
+                .directories
+                .insert(dir.clone(), Self::config_from_blob(repo, *oid)?);
+        }
+        Ok(config)
+    }
+
+    /// Locates the `.codealong.yml` files in subdirectories of `tree` and
+    /// returns their directories along with their blob ids.
+    ///
+    /// Results are memoized per subtree in `cache`, so that walking the trees
+    /// of consecutive commits only descends into subtrees which changed.
+    pub fn find_directory_config_blobs(
+        repo: &Repository,
+        tree: &Tree,
+        cache: &mut HashMap<Oid, Vec<(String, Oid)>>,
+    ) -> Result<Vec<(String, Oid)>> {
+        let mut res = Vec::new();
+        for entry in tree.iter() {
+            if entry.kind() != Some(ObjectType::Tree) {
+                continue;
+            }
+            let name = match entry.name() {
+                Some(name) => name,
+                None => continue,
+            };
+            if !cache.contains_key(&entry.id()) {
+                let subtree = repo.find_tree(entry.id())?;
+                let mut configs = Self::find_directory_config_blobs(repo, &subtree, cache)?;
+                if let Some(oid) = subtree
+                    .get_name(Self::DEFAULT_PATH)
+                    .filter(|entry| entry.kind() == Some(ObjectType::Blob))
+                    .map(|entry| entry.id())
+                {
+                    configs.insert(0, (String::new(), oid));
+                }
+                cache.insert(entry.id(), configs);
+            }
+            res.extend(cache[&entry.id()].iter().map(|(dir, oid)| {
+                if dir.is_empty() {
+                    (name.to_owned(), *oid)
+                } else {
+                    (format!("{}/{}", name, dir), *oid)
+                }
+            }));
+        }
+        Ok(res)
+    }
+
+    /// Locates the config file within `tree` and returns its blob id.
+    pub fn find_config_blob(tree: &Tree) -> Option<Oid> {
+        [Self::DEFAULT_PATH, Self::LEGACY_PATH]
+            .iter()
+            .filter_map(|path| tree.get_path(Path::new(path)).ok())
+            .find(|entry| entry.kind() == Some(ObjectType::Blob))
+            .map(|entry| entry.id())
+    }
+
+    pub fn config_from_blob(repo: &Repository, oid: Oid) -> Result<Config> {
+        let blob = repo.find_blob(oid)?;
+        let mut config = Config::from_slice(blob.content())?;
+        config.extends.clear();
         Ok(config)
     }
 
@@ -81,12 +188,44 @@ mod tests {
     fn test_from_dir_without_config() {
         let config = RepoConfig::from_dir(Path::new("fixtures/repos/simple")).unwrap();
         assert_eq!(config.repo.name, "simple");
+        assert!(config.config.files.is_empty());
     }
 
     #[test]
     fn test_from_dir_with_config() {
         let config = RepoConfig::from_dir(Path::new("fixtures/repos/bare_config")).unwrap();
         assert_eq!(config.repo.name, "bare_config");
+        assert_eq!(
+            config.config.files.get("README.md").unwrap().tags,
+            vec!["readme".to_owned()]
+        );
+    }
+
+    #[test]
+    fn test_config_from_refs() -> Result<()> {
+        let repo = Repository::open("fixtures/repos/bare_config")?;
+        let config =
+            RepoConfig::config_from_refs(&repo, &["refs/remotes/origin/master".to_owned()])?;
+        assert!(config.unwrap().files.contains_key("README.md"));
+        let config = RepoConfig::config_from_refs(&repo, &["refs/heads/missing".to_owned()])?;
+        assert!(config.is_some());
+        let repo = Repository::open("fixtures/repos/simple")?;
+        assert!(RepoConfig::config_from_refs(&repo, &[])?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_from_tree_with_directories() -> Result<()> {
+        let repo = Repository::open("fixtures/repos/nested_config")?;
+        let tree = repo.head()?.peel_to_tree()?;
+        let config = RepoConfig::config_from_tree(&repo, &tree)?.unwrap();
+        assert!(config.files.contains_key("**/*.rb"));
+        let dirs: Vec<&str> = config.directories.keys().map(|dir| dir.as_str()).collect();
+        assert_eq!(dirs, vec!["services/api", "services/web"]);
+        assert!(config.directories["services/api"]
+            .files
+            .contains_key("vendor/**"));
+        Ok(())
     }
 
     #[test]
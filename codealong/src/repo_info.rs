@@ -19,8 +19,20 @@ pub struct RepoInfo {
     #[serde(default)]
     pub clone_url: String,
 
+    /// Refs or ref patterns to analyze, e.g. `refs/remotes/origin/*`. The
+    /// default branch is analyzed if empty.
     #[serde(default)]
     pub refs: Vec<String>,
+
+    /// The default branch, either as a branch name or a full ref. Detected
+    /// from `origin/HEAD` if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_branch: Option<String>,
+
+    /// Glob patterns of the tag names which are releases, e.g. `v*`. Every
+    /// tag is a release if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub release_tags: Vec<String>,
 }
 
 impl RepoInfo {
@@ -100,6 +112,17 @@ impl RepoInfo {
         PartialRepoInfo {
             name: self.name.clone(),
             fork: self.fork,
+            monorepo: None,
+        }
+    }
+
+    /// The info included with the events of one of the repo's projects,
+    /// which is named after the project.
+    pub fn project_partial(&self, project: &str) -> PartialRepoInfo {
+        PartialRepoInfo {
+            name: project.to_owned(),
+            fork: self.fork,
+            monorepo: Some(self.name.clone()),
         }
     }
 }
@@ -111,7 +134,9 @@ impl Default for RepoInfo {
             fork: false,
             github_name: None,
             clone_url: "".to_owned(),
-            refs: vec!["refs/remotes/origin/master".to_owned()],
+            refs: Vec::new(),
+            default_branch: None,
+            release_tags: Vec::new(),
         }
     }
 }
@@ -121,6 +146,10 @@ impl Default for RepoInfo {
 pub struct PartialRepoInfo {
     name: String,
     fork: bool,
+
+    /// The repo a project belongs to, if the events are those of a project.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    monorepo: Option<String>,
 }
 
 #[cfg(test)]
@@ -0,0 +1,186 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use chrono::{DateTime, Utc};
+use git2::{Commit, Oid, Repository, Sort};
+use regex::Regex;
+
+use crate::error::*;
+use crate::utils::convert_time;
+
+/// Which commits revert which.
+pub struct Reverts {
+    reverts: HashMap<Oid, Oid>,
+    reverted_by: HashMap<Oid, Oid>,
+}
+
+impl Reverts {
+    /// Detects the reverts among the commits reachable from `tips` but not
+    /// from `hide`, skipping those authored before `since`. A commit reverts
+    /// another if its message says `This reverts commit <sha>`, or if it
+    /// changes exactly the same files back to exactly the same contents as
+    /// before the other commit.
+    ///
+    /// The latter is only detected when both commits are walked, whereas
+    /// messages may refer to commits analyzed previously.
+    pub fn resolve(
+        repo: &Repository,
+        tips: &[Oid],
+        hide: &[Oid],
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Reverts> {
+        let mut result = Reverts::empty();
+        let mut revwalk = repo.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE);
+        for oid in tips {
+            revwalk.push(*oid)?;
+        }
+        for oid in hide {
+            revwalk.hide(*oid)?;
+        }
+        // the most recent commit making each change, by signature
+        let mut changes: HashMap<u64, Oid> = HashMap::new();
+        for oid in revwalk {
+            let commit = repo.find_commit(oid?)?;
+            if commit.parent_count() > 1 {
+                continue;
+            }
+            if let Some(since) = since {
+                if convert_time(&commit.author().when()) < since {
+                    continue;
+                }
+            }
+            let (forward, inverse) = match change_signatures(repo, &commit)? {
+                Some(signatures) => signatures,
+                None => continue,
+            };
+            let reverted = reverted_commit_id(&commit).or_else(|| {
+                changes
+                    .get(&inverse)
+                    .cloned()
+                    .filter(|original| !result.reverted_by.contains_key(original))
+            });
+            if let Some(original) = reverted {
+                result.reverts.insert(commit.id(), original);
+                result.reverted_by.insert(original, commit.id());
+            }
+            changes.insert(forward, commit.id());
+        }
+        Ok(result)
+    }
+
+    /// No reverts, so that commits are not marked as reverting any.
+    pub(crate) fn empty() -> Reverts {
+        Reverts {
+            reverts: HashMap::new(),
+            reverted_by: HashMap::new(),
+        }
+    }
+
+    /// The commit reverted by `oid`.
+    pub fn reverts(&self, oid: Oid) -> Option<Oid> {
+        self.reverts.get(&oid).cloned()
+    }
+
+    /// The commit reverting `oid`.
+    pub fn reverted_by(&self, oid: Oid) -> Option<Oid> {
+        self.reverted_by.get(&oid).cloned()
+    }
+}
+
+/// Parses the commit referenced by the `This reverts commit <sha>` line git
+/// adds to the message of reverts.
+fn reverted_commit_id(commit: &Commit) -> Option<Oid> {
+    lazy_static! {
+        static ref REVERT_REGEX: Regex =
+            Regex::new(r"(?m)^This reverts commit ([0-9a-f]{40})").unwrap();
+    }
+    REVERT_REGEX
+        .captures(commit.message()?)
+        .and_then(|captures| Oid::from_str(&captures[1]).ok())
+}
+
+/// Hashes the files changed by `commit` along with their contents before and
+/// after, as well as the inverse, which is the signature of the changes of a
+/// commit reverting it. Returns `None` if nothing was changed.
+fn change_signatures(repo: &Repository, commit: &Commit) -> Result<Option<(u64, u64)>> {
+    let parent_tree = match commit.parents().next() {
+        Some(parent) => Some(parent.tree()?),
+        None => None,
+    };
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+    let mut changes: Vec<(Vec<u8>, Oid, Vec<u8>, Oid)> = diff
+        .deltas()
+        .map(|delta| {
+            let path = |file: git2::DiffFile| {
+                file.path_bytes()
+                    .map(|path| path.to_owned())
+                    .unwrap_or_default()
+            };
+            (
+                path(delta.old_file()),
+                delta.old_file().id(),
+                path(delta.new_file()),
+                delta.new_file().id(),
+            )
+        })
+        .collect();
+    if changes.is_empty() {
+        return Ok(None);
+    }
+    changes.sort();
+    let mut forward = DefaultHasher::new();
+    changes.hash(&mut forward);
+    let mut inverse_changes: Vec<_> = changes
+        .into_iter()
+        .map(|(old_path, old_id, new_path, new_id)| (new_path, new_id, old_path, old_id))
+        .collect();
+    inverse_changes.sort();
+    let mut inverse = DefaultHasher::new();
+    inverse_changes.hash(&mut inverse);
+    Ok(Some((forward.finish(), inverse.finish())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/reverts")?;
+        let head = repo.head()?.peel_to_commit()?;
+        let reverts = Reverts::resolve(&repo, &[head.id()], &[], None)?;
+
+        let summary = |oid: Option<Oid>| -> Result<Option<String>> {
+            Ok(match oid {
+                Some(oid) => Some(repo.find_commit(oid)?.summary().unwrap().to_owned()),
+                None => None,
+            })
+        };
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push(head.id())?;
+        let mut found = Vec::new();
+        for oid in revwalk {
+            let oid = oid?;
+            if let Some(reverted) = summary(reverts.reverts(oid))? {
+                assert_eq!(
+                    reverts.reverted_by(reverts.reverts(oid).unwrap()),
+                    Some(oid)
+                );
+                found.push((summary(Some(oid))?.unwrap(), reverted));
+            }
+        }
+        assert_eq!(
+            found,
+            vec![
+                ("Remove b".to_owned(), "Add b".to_owned()),
+                (
+                    "Revert \"Add feature\"".to_owned(),
+                    "Add feature".to_owned()
+                ),
+            ]
+        );
+        Ok(())
+    }
+}
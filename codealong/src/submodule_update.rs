@@ -0,0 +1,168 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use git2::{Commit, DiffDelta, DiffFile, Oid, Repository, Tree};
+
+use crate::error::*;
+use crate::repo_info::RepoInfo;
+
+/// A change of the commit a submodule points to. Such changes are not
+/// analyzed line by line.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubmoduleUpdate {
+    pub path: String,
+
+    /// The url of the submodule according to `.gitmodules`.
+    #[serde(default)]
+    pub url: Option<String>,
+
+    /// The commit the submodule pointed to before and after, respectively.
+    /// Not set if the submodule was added or removed.
+    pub old_commit: Option<String>,
+    pub new_commit: Option<String>,
+
+    /// The commits brought in, newest first, if the submodule repository is
+    /// available.
+    #[serde(default)]
+    pub commits: Option<Vec<String>>,
+}
+
+impl SubmoduleUpdate {
+    pub fn new(path: &str, old_commit: Option<Oid>, new_commit: Option<Oid>) -> SubmoduleUpdate {
+        SubmoduleUpdate {
+            path: path.to_owned(),
+            url: None,
+            old_commit: old_commit.map(|oid| oid.to_string()),
+            new_commit: new_commit.map(|oid| oid.to_string()),
+            commits: None,
+        }
+    }
+
+    /// Lists the commits brought in by the update, i.e. those reachable from
+    /// the new commit but not from the old one, in `repo`.
+    pub fn resolve_commits(&mut self, repo: &Repository) -> Result<()> {
+        let new_commit = match self.new_commit {
+            Some(ref new_commit) => Oid::from_str(new_commit)?,
+            None => return Ok(()),
+        };
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push(new_commit)?;
+        if let Some(ref old_commit) = self.old_commit {
+            revwalk.hide(Oid::from_str(old_commit)?)?;
+        }
+        self.commits = Some(
+            revwalk
+                .map(|oid| Ok(oid?.to_string()))
+                .collect::<Result<Vec<_>>>()?,
+        );
+        Ok(())
+    }
+}
+
+/// The paths of the submodules of a commit and its parent according to their
+/// `.gitmodules` files, read once per diff so that its files need not be
+/// looked up in the trees one by one.
+pub struct SubmodulePaths {
+    old: HashSet<String>,
+    new: HashSet<String>,
+}
+
+impl SubmodulePaths {
+    pub fn new(
+        repo: &Repository,
+        commit: &Commit,
+        parent: Option<&Commit>,
+    ) -> Result<SubmodulePaths> {
+        let old = match parent {
+            Some(parent) => submodule_urls(repo, &parent.tree()?)?,
+            None => HashMap::new(),
+        };
+        let new = submodule_urls(repo, &commit.tree()?)?;
+        Ok(SubmodulePaths {
+            old: old.into_iter().map(|(path, _)| path).collect(),
+            new: new.into_iter().map(|(path, _)| path).collect(),
+        })
+    }
+
+    /// The update of the submodule at `path`, if it is one on either side of
+    /// `diff_delta`.
+    pub fn update(&self, diff_delta: &DiffDelta, path: &str) -> Option<SubmoduleUpdate> {
+        // A file replaced by a submodule or vice versa is split into a
+        // deletion and an addition, whose absent side has a zero id.
+        let submodule_commit = |paths: &HashSet<String>, file: DiffFile| {
+            if !file.id().is_zero() && paths.contains(path) {
+                Some(file.id())
+            } else {
+                None
+            }
+        };
+        let old_commit = submodule_commit(&self.old, diff_delta.old_file());
+        let new_commit = submodule_commit(&self.new, diff_delta.new_file());
+        if old_commit.is_none() && new_commit.is_none() {
+            return None;
+        }
+        Some(SubmoduleUpdate::new(path, old_commit, new_commit))
+    }
+}
+
+/// The url of each submodule listed in the `.gitmodules` file of `tree`,
+/// keyed by path.
+pub fn submodule_urls(repo: &Repository, tree: &Tree) -> Result<HashMap<String, String>> {
+    let entry = match tree.get_path(Path::new(".gitmodules")) {
+        Ok(entry) => entry,
+        Err(_) => return Ok(HashMap::new()),
+    };
+    let blob = repo.find_blob(entry.id())?;
+    let mut result = HashMap::new();
+    let mut path = None;
+    let mut url = None;
+    for line in String::from_utf8_lossy(blob.content()).lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            if let (Some(path), Some(url)) = (path.take(), url.take()) {
+                result.insert(path, url);
+            }
+            continue;
+        }
+        let mut parts = line.splitn(2, '=');
+        match (parts.next().map(|key| key.trim()), parts.next()) {
+            (Some("path"), Some(value)) => path = Some(value.trim().to_owned()),
+            (Some("url"), Some(value)) => url = Some(value.trim().to_owned()),
+            _ => {}
+        }
+    }
+    if let (Some(path), Some(url)) = (path, url) {
+        result.insert(path, url);
+    }
+    Ok(result)
+}
+
+/// The name a submodule url refers to a repository by, e.g. `org/repo` for
+/// `git@github.com:org/repo.git`, to match it with the repos of a workspace.
+pub fn submodule_repo_name(url: &str) -> Option<String> {
+    RepoInfo::from_url(url)
+        .ok()
+        .map(|repo_info| repo_info.name.trim_end_matches(".git").to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_submodule_urls() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/submodules")?;
+        let tree = repo.head()?.peel_to_tree()?;
+        let urls = submodule_urls(&repo, &tree)?;
+        assert_eq!(urls["vendor/lib"], "https://github.com/example/lib.git");
+        assert_eq!(
+            submodule_repo_name("https://github.com/example/lib.git"),
+            Some("example/lib".to_owned())
+        );
+        assert_eq!(
+            submodule_repo_name("git@github.com:example/lib.git"),
+            Some("example/lib".to_owned())
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use git2::{Commit, DiffOptions, Repository};
+
+use crate::error::*;
+use crate::git_blame::blame_ranges;
+use crate::utils::path_from_bytes;
+use crate::working_config::WorkingConfig;
+
+/// The commits which last changed the lines a bug fix deleted or modified in
+/// the file at `path`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlamedLines {
+    /// The path of the file. Paths which are not valid UTF-8 are stored
+    /// lossily.
+    pub path: String,
+
+    pub commits: Vec<String>,
+}
+
+/// Blames the lines of each file changed by the bug fix `commit`, following
+/// the SZZ algorithm: the lines the fix deletes or modifies are blamed as of
+/// its parent, and the commits which last changed them are the ones
+/// introducing the bug. Ignored files are skipped, as are merge commits.
+pub fn blame_bug_fix(
+    repo: &Repository,
+    commit: &Commit,
+    config: &WorkingConfig,
+) -> Result<Vec<BlamedLines>> {
+    if commit.parent_count() != 1 {
+        return Ok(Vec::new());
+    }
+    let parent = commit.parent(0)?;
+    let mut diff_opts = DiffOptions::new();
+    diff_opts.context_lines(0);
+    let diff = repo.diff_tree_to_tree(
+        Some(&parent.tree()?),
+        Some(&commit.tree()?),
+        Some(&mut diff_opts),
+    )?;
+    let mut result = Vec::new();
+    for (index, delta) in diff.deltas().enumerate() {
+        let old_path = match delta.old_file().path_bytes() {
+            Some(path) if !delta.old_file().id().is_zero() => path,
+            _ => continue,
+        };
+        let ignored = config
+            .config_for_path(old_path)
+            .map(|c| c.ignore())
+            .unwrap_or(false);
+        if ignored {
+            continue;
+        }
+        let patch = match git2::Patch::from_diff(&diff, index)? {
+            Some(patch) => patch,
+            None => continue,
+        };
+        let mut ranges = Vec::new();
+        for hunk_index in 0..patch.num_hunks() {
+            let (hunk, _) = patch.hunk(hunk_index)?;
+            if hunk.old_lines() > 0 {
+                let start = hunk.old_start() as usize;
+                ranges.push((start, start + hunk.old_lines() as usize - 1));
+            }
+        }
+        if ranges.is_empty() {
+            continue;
+        }
+        let commits = blame_ranges(repo, &parent.id(), &path_from_bytes(old_path), &ranges)?;
+        result.push(BlamedLines {
+            path: String::from_utf8_lossy(old_path).into_owned(),
+            commits: commits.iter().map(|oid| oid.to_string()).collect(),
+        });
+    }
+    Ok(result)
+}
+
+/// The distinct commits blamed for the lines of the files whose path
+/// satisfies `include`, i.e. those which introduced the bug.
+pub fn bug_introducing_commits(
+    blamed_lines: &[BlamedLines],
+    include: &dyn Fn(&str) -> bool,
+) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    for blamed in blamed_lines.iter().filter(|blamed| include(&blamed.path)) {
+        for commit in &blamed.commits {
+            if !result.contains(commit) {
+                result.push(commit.clone());
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Oid;
+
+    #[test]
+    fn test_bug_introducing_commits() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/bug_fixes")?;
+        let config = WorkingConfig::default();
+        let fix = repo.head()?.peel_to_commit()?;
+        assert!(config.is_bug_fix(fix.message().unwrap()));
+        let blamed_lines = blame_bug_fix(&repo, &fix, &config)?;
+        let summaries = bug_introducing_commits(&blamed_lines, &|_| true)
+            .into_iter()
+            .map(|id| {
+                Ok(repo
+                    .find_commit(Oid::from_str(&id)?)?
+                    .summary()
+                    .unwrap()
+                    .to_owned())
+            })
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(summaries, vec!["Add docs"]);
+        assert!(bug_introducing_commits(&blamed_lines, &|_| false).is_empty());
+
+        let parent = fix.parent(0)?;
+        assert!(!config.is_bug_fix(parent.message().unwrap()));
+        Ok(())
+    }
+}
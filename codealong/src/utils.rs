@@ -3,7 +3,13 @@ use chrono::DateTime;
 use git2::Time;
 
 use crate::error::*;
+use std::borrow::Cow;
 use std::env;
+#[cfg(unix)]
+use std::ffi::OsStr;
+use std::path::Path;
+#[cfg(not(unix))]
+use std::path::PathBuf;
 
 type CargoResult<T> = Result<T>;
 type CargoError = Error;
@@ -221,6 +227,24 @@ where
 }
 
 pub fn convert_time(time: &Time) -> DateTime<Utc> {
+    convert_local_time(time).with_timezone(&Utc)
+}
+
+/// Same as `convert_time`, but keeps the offset the time was recorded with.
+pub fn convert_local_time(time: &Time) -> DateTime<FixedOffset> {
     let tz = FixedOffset::east(time.offset_minutes() * 60);
-    tz.timestamp(time.seconds(), 0).with_timezone(&Utc)
+    tz.timestamp(time.seconds(), 0)
+}
+
+/// The path git stores as `bytes`. Paths are arbitrary bytes on Unix, and
+/// are converted lossily elsewhere.
+#[cfg(unix)]
+pub fn path_from_bytes(bytes: &[u8]) -> Cow<Path> {
+    use std::os::unix::ffi::OsStrExt;
+    Cow::Borrowed(Path::new(OsStr::from_bytes(bytes)))
+}
+
+#[cfg(not(unix))]
+pub fn path_from_bytes(bytes: &[u8]) -> Cow<Path> {
+    Cow::Owned(PathBuf::from(String::from_utf8_lossy(bytes).into_owned()))
 }
@@ -1,12 +1,39 @@
-use glob::Pattern;
 use std::collections::HashSet;
 
-use crate::config::{Config, ContributorConfig, GlobConfig};
+use regex::bytes::RegexSet;
+
+use crate::config::{Config, ContributorConfig, GlobConfig, MergeMode, WorkingHours};
 use crate::contributor::Contributor;
 use crate::identity::Identity;
+use crate::path_matcher::{compile_regex_set, PathMatcher};
+
+lazy_static! {
+    static ref DEFAULT_WORKING_HOURS: WorkingHours = WorkingHours::default();
+}
 
 pub struct WorkingConfig {
     config: Config,
+    file_matcher: PathMatcher,
+    directories: Vec<DirectoryConfig>,
+    bug_fix_matcher: RegexSet,
+    modules: Vec<ModuleConfig>,
+}
+
+/// The paths of a module of the `modules` section.
+struct ModuleConfig {
+    name: String,
+    /// Directories without a trailing `/`.
+    prefixes: Vec<Vec<u8>>,
+    matcher: PathMatcher,
+}
+
+/// The `files` section of a config committed in a subdirectory.
+struct DirectoryConfig {
+    /// The directory with a trailing `/`.
+    prefix: Vec<u8>,
+    files: Vec<GlobConfig>,
+    file_matcher: PathMatcher,
+    replace: bool,
 }
 
 impl WorkingConfig {
@@ -14,34 +41,101 @@ impl WorkingConfig {
         if config.merge_defaults {
             config.merge(Config::base());
         }
-        WorkingConfig { config }
+        let file_matcher = compile_file_matcher(config.files.iter());
+        let mut directories: Vec<DirectoryConfig> = std::mem::take(&mut config.directories)
+            .into_iter()
+            .map(|(dir, dir_config)| {
+                let dir = dir.trim_matches('/');
+                let prefix = if dir.is_empty() {
+                    Vec::new()
+                } else {
+                    format!("{}/", dir).into_bytes()
+                };
+                DirectoryConfig {
+                    prefix,
+                    file_matcher: compile_file_matcher(dir_config.files.iter()),
+                    files: dir_config.files.into_iter().map(|(_, c)| c).collect(),
+                    replace: dir_config.merge_strategy.files == MergeMode::Replace,
+                }
+            })
+            .collect();
+        // Apply shallower directories first so that the nearest config wins.
+        directories.sort_by_key(|d| d.prefix.iter().filter(|b| **b == b'/').count());
+        // patterns which fail to compile never match
+        let (bug_fix_matcher, _) =
+            compile_regex_set(config.bug_fix_patterns.iter().cloned().map(Some));
+        let modules = config
+            .modules
+            .iter()
+            .map(|(name, paths)| {
+                let (patterns, prefixes): (Vec<&String>, Vec<&String>) =
+                    paths.iter().partition(|path| is_pattern(path));
+                ModuleConfig {
+                    name: name.clone(),
+                    prefixes: prefixes
+                        .into_iter()
+                        .map(|prefix| prefix.trim_matches('/').as_bytes().to_vec())
+                        .collect(),
+                    matcher: PathMatcher::new(patterns.into_iter().map(|p| (p.as_str(), false))),
+                }
+            })
+            .collect();
+        WorkingConfig {
+            config,
+            file_matcher,
+            directories,
+            bug_fix_matcher,
+            modules,
+        }
     }
 
     pub fn churn_cutoff(&self) -> u64 {
         self.config.churn_cutoff
     }
 
+    /// Whether a commit with `message` fixes a bug according to the
+    /// `bug_fix_patterns`.
+    pub fn is_bug_fix(&self, message: &str) -> bool {
+        self.bug_fix_matcher.is_match(message.as_bytes())
+    }
+
     pub fn default() -> WorkingConfig {
         Self::new(Config::default())
     }
 
     pub fn config_for_file(&self, path: &str) -> Option<FileConfig> {
-        let config = &self.config;
-        let glob_configs: Vec<&GlobConfig> = config
+        self.config_for_path(path.as_bytes())
+    }
+
+    /// Like `config_for_file`, but accepts paths which are not valid UTF-8.
+    ///
+    /// Configs of directories containing `path` are layered on top of the
+    /// repository config, nearest last, so that their weights take
+    /// precedence. A directory config with a `replace` files merge strategy
+    /// discards the file configs of all enclosing directories.
+    pub fn config_for_path(&self, path: &[u8]) -> Option<FileConfig> {
+        let mut glob_configs: Vec<&GlobConfig> = self
+            .config
             .files
-            .iter()
-            .filter_map(|(s, config)| {
-                if let Ok(pattern) = Pattern::new(&s) {
-                    if pattern.matches(path) {
-                        Some(config)
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            })
+            .values()
+            .zip(self.file_matcher.matches(path))
+            .filter_map(|(config, matched)| if matched { Some(config) } else { None })
             .collect();
+        for dir in &self.directories {
+            if !path.starts_with(&dir.prefix) {
+                continue;
+            }
+            if dir.replace {
+                glob_configs.clear();
+            }
+            let relative_path = &path[dir.prefix.len()..];
+            glob_configs.extend(
+                dir.files
+                    .iter()
+                    .zip(dir.file_matcher.matches(relative_path))
+                    .filter_map(|(config, matched)| if matched { Some(config) } else { None }),
+            );
+        }
         if glob_configs.is_empty() {
             None
         } else {
@@ -49,6 +143,27 @@ impl WorkingConfig {
         }
     }
 
+    /// The module `path` belongs to according to the `modules` section, or
+    /// else its leading directories if a `module_depth` is set.
+    pub fn module_for_path(&self, path: &[u8]) -> Option<String> {
+        for module in &self.modules {
+            let prefixed = module.prefixes.iter().any(|prefix| {
+                path.starts_with(prefix)
+                    && (path.len() == prefix.len() || path[prefix.len()] == b'/')
+            });
+            if prefixed || module.matcher.matches(path).into_iter().any(|m| m) {
+                return Some(module.name.clone());
+            }
+        }
+        let depth = self.config.module_depth?;
+        let directories: Vec<&[u8]> = path.split(|b| *b == b'/').collect();
+        let depth = depth.min(directories.len() - 1);
+        if depth == 0 {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&directories[..depth].join(&b'/')).into_owned())
+    }
+
     pub fn config_for_identity(&self, identity: &Identity) -> Option<&ContributorConfig> {
         let config = &self.config;
         for contributor_config in &config.contributors {
@@ -89,6 +204,26 @@ impl WorkingConfig {
         }
     }
 
+    /// The working hours of the contributor with `identity`: their own if
+    /// configured, else those of the first of their teams which has any, else
+    /// the default ones.
+    pub fn working_hours_for_identity(&self, identity: &Identity) -> &WorkingHours {
+        let contributor_config = self.config_for_identity(identity);
+        if let Some(working_hours) = contributor_config.and_then(|c| c.working_hours.as_ref()) {
+            return working_hours;
+        }
+        contributor_config
+            .and_then(|c| {
+                c.contributor
+                    .teams
+                    .iter()
+                    .filter_map(|team| self.config.team_working_hours.get(team))
+                    .next()
+            })
+            .or(self.config.working_hours.as_ref())
+            .unwrap_or(&DEFAULT_WORKING_HOURS)
+    }
+
     pub fn is_known(&self, identity: &Identity) -> bool {
         self.config_for_identity(identity).is_some()
     }
@@ -98,6 +233,13 @@ impl WorkingConfig {
     }
 }
 
+/// Whether a module path is a pattern rather than a plain directory.
+fn is_pattern(path: &str) -> bool {
+    path.starts_with("regex:")
+        || path.starts_with('!')
+        || path.contains(|c| c == '*' || c == '?' || c == '[')
+}
+
 /// Represents multiple underlying glob-level configurations. A file can have
 /// mulitiple configurations if it matches multiple globs.
 pub struct FileConfig<'a> {
@@ -126,6 +268,16 @@ impl<'a> FileConfig<'a> {
     }
 }
 
+/// Compiles the patterns of `files` in order.
+fn compile_file_matcher<'a, I>(files: I) -> PathMatcher
+where
+    I: Iterator<Item = (&'a String, &'a GlobConfig)>,
+{
+    PathMatcher::new(
+        files.map(|(pattern, glob_config)| (pattern.as_str(), glob_config.case_insensitive)),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -161,6 +313,33 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_module_for_path() {
+        let mut config = Config::default();
+        config.modules.insert(
+            "api".to_owned(),
+            vec!["app/api/".to_owned(), "lib/**/api/*.rb".to_owned()],
+        );
+        config
+            .modules
+            .insert("app".to_owned(), vec!["app".to_owned()]);
+        let config = WorkingConfig::new(config);
+        let module = |path: &str| config.module_for_path(path.as_bytes());
+        assert_eq!(module("app/api/users.rb"), Some("api".to_owned()));
+        assert_eq!(module("lib/v1/api/users.rb"), Some("api".to_owned()));
+        assert_eq!(module("app/models/user.rb"), Some("app".to_owned()));
+        assert_eq!(module("application.rb"), None);
+
+        let mut config = Config::default();
+        config.module_depth = Some(2);
+        let config = WorkingConfig::new(config);
+        let module = |path: &str| config.module_for_path(path.as_bytes());
+        assert_eq!(module("src/bin/main.rs"), Some("src/bin".to_owned()));
+        assert_eq!(module("src/bin/tools/fmt.rs"), Some("src/bin".to_owned()));
+        assert_eq!(module("src/lib.rs"), Some("src".to_owned()));
+        assert_eq!(module("README.md"), None);
+    }
+
     #[test]
     fn test_base() {
         let config = WorkingConfig::new(Config::base());
@@ -180,6 +359,7 @@ mod tests {
                 weight: 1.0,
                 ignore: false,
                 tags: vec!["ruby".to_string()],
+                ..GlobConfig::default()
             },
         );
 
@@ -189,6 +369,7 @@ mod tests {
                 weight: 0.5,
                 ignore: false,
                 tags: vec!["rspec".to_string()],
+                ..GlobConfig::default()
             },
         );
 
@@ -198,6 +379,7 @@ mod tests {
                 weight: 1.0,
                 ignore: true,
                 tags: vec![],
+                ..GlobConfig::default()
             },
         );
 
@@ -223,4 +405,105 @@ mod tests {
         assert!(file_config.weight() == 1.0);
         assert!(file_config.ignore());
     }
+
+    #[test]
+    fn test_pattern_kinds() {
+        let mut config = Config::default();
+        config.merge_defaults = false;
+
+        config.files.insert(
+            "!vendor/**".to_string(),
+            GlobConfig {
+                tags: vec!["first-party".to_string()],
+                ..GlobConfig::default()
+            },
+        );
+
+        config.files.insert(
+            r"regex:^db/migrate/\d+_.*\.rb$".to_string(),
+            GlobConfig {
+                tags: vec!["migration".to_string()],
+                ..GlobConfig::default()
+            },
+        );
+
+        config.files.insert(
+            "**/*.md".to_string(),
+            GlobConfig {
+                tags: vec!["docs".to_string()],
+                case_insensitive: true,
+                ..GlobConfig::default()
+            },
+        );
+
+        let config = WorkingConfig::new(config);
+
+        let tags = |path: &[u8]| -> HashSet<String> {
+            config
+                .config_for_path(path)
+                .map(|c| c.tags().into_iter().map(|t| t.to_string()).collect())
+                .unwrap_or_default()
+        };
+
+        assert!(config.config_for_file("vendor/lib/README.txt").is_none());
+        assert_eq!(
+            tags(b"db/migrate/001_init.rb"),
+            ["first-party", "migration"]
+                .iter()
+                .map(|t| t.to_string())
+                .collect()
+        );
+        assert_eq!(
+            tags(b"vendor/docs/README.MD"),
+            ["docs"].iter().map(|t| t.to_string()).collect()
+        );
+        assert_eq!(
+            tags(b"caf\xe9/notes.md"),
+            ["first-party", "docs"]
+                .iter()
+                .map(|t| t.to_string())
+                .collect()
+        );
+    }
+
+    #[test]
+    fn test_directory_configs() -> Result<()> {
+        let mut config =
+            Config::from_slice(b"merge_defaults: false\nfiles:\n  '**/*.rb':\n    tags: [ruby]\n")?;
+        config.directories.insert(
+            "services/api".to_string(),
+            Config::from_slice(
+                b"files:\n  '**/*.rb':\n    tags: [team-api]\n    weight: 0.5\n  'vendor/**':\n    ignore: true\n",
+            )?,
+        );
+        config.directories.insert(
+            "services/web/".to_string(),
+            Config::from_slice(
+                b"merge_strategy:\n  files: replace\nfiles:\n  '*.rb':\n    tags: [team-web]\n",
+            )?,
+        );
+        let config = WorkingConfig::new(config);
+
+        let file_config = config.config_for_file("services/api/app.rb").unwrap();
+        assert_eq!(
+            file_config.tags(),
+            ["ruby", "team-api"].iter().cloned().collect()
+        );
+        assert_eq!(file_config.weight(), 0.5);
+        assert!(!file_config.ignore());
+
+        assert!(config
+            .config_for_file("services/api/vendor/lib.rb")
+            .unwrap()
+            .ignore());
+        assert!(config.config_for_file("vendor/lib.rb").is_some());
+        assert!(!config.config_for_file("vendor/lib.rb").unwrap().ignore());
+
+        let file_config = config.config_for_file("services/web/index.rb").unwrap();
+        assert_eq!(file_config.tags(), ["team-web"].iter().cloned().collect());
+
+        let file_config = config.config_for_file("services/apiary/app.rb").unwrap();
+        assert_eq!(file_config.tags(), ["ruby"].iter().cloned().collect());
+        Ok(())
+    }
 }
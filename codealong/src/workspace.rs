@@ -13,6 +13,7 @@ use crate::workspace_config::{RepoEntry, WorkspaceConfig};
 pub struct Workspace {
     dir: PathBuf,
     config: WorkspaceConfig,
+    base_config: Config,
 }
 
 impl Workspace {
@@ -42,11 +43,16 @@ impl Workspace {
             Ok(config) => config,
             Err(_) => WorkspaceConfig::default(),
         };
-        Ok(Self::new(dir, config))
+        Self::new(dir, config)
     }
 
-    pub fn new(dir: PathBuf, config: WorkspaceConfig) -> Workspace {
-        Workspace { dir, config }
+    pub fn new(dir: PathBuf, config: WorkspaceConfig) -> Result<Workspace> {
+        let base_config = config.resolved_config()?;
+        Ok(Workspace {
+            dir,
+            config,
+            base_config,
+        })
     }
 
     pub fn repos_dir(&self) -> &Path {
@@ -68,7 +74,9 @@ impl Workspace {
 
     pub fn repo(&self, entry: &RepoEntry) -> Repo {
         let path = self.repo_dir(&entry);
-        Repo::new(self.config.config.clone(), path, entry.repo_info.clone())
+        let mut repo = Repo::new(self.base_config.clone(), path, entry.repo_info.clone());
+        repo.set_projects(entry.projects.clone());
+        repo
     }
 
     pub fn add(&mut self, repo_info: RepoInfo, path: Option<String>) -> Result<()> {
@@ -79,15 +87,21 @@ impl Workspace {
         self.config.get_entry(name).map(|entry| self.repo(entry))
     }
 
+    /// Layers an additional config on top of the workspace config for the
+    /// duration of this run. Additional configs are not saved.
     pub fn add_config(&mut self, config: Config) {
-        self.config.config.merge(config);
+        self.base_config.merge(config);
     }
 
     pub fn add_contributor(&mut self, contributor: Contributor) {
-        self.config.config.contributors.push(ContributorConfig {
+        let contributor_config = ContributorConfig {
             contributor,
             ..Default::default()
-        });
+        };
+        self.base_config
+            .contributors
+            .push(contributor_config.clone());
+        self.config.config.contributors.push(contributor_config);
     }
 
     pub fn save(&self) -> Result<()> {
@@ -3,6 +3,7 @@ use std::path::{Path, PathBuf};
 
 use crate::config::Config;
 use crate::error::*;
+use crate::project::ProjectConfig;
 use crate::repo_info::RepoInfo;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -39,6 +40,17 @@ impl WorkspaceConfig {
         Ok(serde_yaml::from_reader::<_, WorkspaceConfig>(file)?)
     }
 
+    /// The workspace-level config with its `extends` entries resolved
+    /// relative to the location of the workspace config file.
+    pub fn resolved_config(&self) -> Result<Config> {
+        let mut config = self.config.clone();
+        match self.path {
+            Some(ref path) if path.exists() => config.resolve_extends_of(path)?,
+            _ => config.resolve_extends(Path::new("."))?,
+        }
+        Ok(config)
+    }
+
     pub fn save(&self) -> Result<()> {
         let file = File::create(self.path.as_ref().ok_or("No path specified")?)?;
         Ok(serde_yaml::to_writer(file, self)?)
@@ -51,6 +63,7 @@ impl WorkspaceConfig {
             repo_info: repo_info,
             ignore: false,
             path,
+            projects: Vec::new(),
         };
 
         match self
@@ -99,6 +112,11 @@ pub struct RepoEntry {
 
     #[serde(default)]
     pub path: Option<String>,
+
+    /// Projects of a monorepo, each analyzed as a repo of its own. Commits
+    /// changing several of them are split by project.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub projects: Vec<ProjectConfig>,
 }
 
 impl RepoEntry {